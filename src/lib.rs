@@ -0,0 +1,4690 @@
+//! A Verlet integrated particle simulation for Bevy. Add [`VerletPlugin`] to an app that already
+//! has the `DefaultPlugins` and a 2D camera.
+
+use std::{
+    any::TypeId,
+    collections::VecDeque,
+    error::Error,
+    fs,
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
+};
+
+use bevy::{
+    prelude::{Commands, *},
+    reflect::ReflectMut,
+    render::texture::DEFAULT_IMAGE_HANDLE,
+    sprite::Mesh2dHandle,
+    utils::{HashMap, HashSet},
+    window::{WindowId, WindowResized},
+};
+use bevy_prototype_lyon::{entity::ShapeBundle, prelude::*};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use rand::{distributions::WeightedIndex, prelude::Distribution, seq::SliceRandom, Rng};
+use serde::Deserialize;
+
+#[derive(Component, Debug, Default, Reflect)]
+#[reflect(Component)]
+struct Point {
+    x: f32,
+    y: f32,
+    old_x: f32,
+    old_y: f32,
+    radius: f32,
+    mass: f32,
+    color: u8,
+    id: i32,
+    acc_x: f32,
+    acc_y: f32,
+    /// How many other points this one touched in the last substep
+    coordination_number: u8,
+    /// Picks per-category settings such as [`CategoryGravity`]
+    category: u32,
+    temperature: f32,
+    /// Concentrations of the two species in [`ReactionDiffusion`]
+    u: f32,
+    v: f32,
+}
+
+#[derive(Resource, Default, Reflect, Debug, PartialEq)]
+#[reflect(Resource)]
+struct Bounds {
+    min_x: i32,
+    max_x: i32,
+    min_y: i32,
+    max_y: i32,
+    /// Rounds off the corners with quarter circles, so points can't wedge into them
+    corner_radius: f32,
+}
+
+enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+impl Bounds {
+    fn new(min_x: i32, min_y: i32, max_x: i32, max_y: i32) -> Self {
+        Bounds {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+            corner_radius: 0.0,
+        }
+    }
+
+    fn with_corner_radius(mut self, corner_radius: f32) -> Self {
+        self.corner_radius = corner_radius;
+        self
+    }
+
+    /// The corner radius actually used, never more than half the shorter side
+    fn effective_corner_radius(&self) -> f32 {
+        let size = self.to_rect().size();
+        self.corner_radius.clamp(0.0, size.min_element() / 2.0)
+    }
+
+    /// Pushes a point that left a rounded corner back onto its arc and bounces it off. Friction
+    /// applies as on the flat walls, the damping is the mean of the two sides meeting at the
+    /// corner. Returns the impulse, like [`Bounds::constrain_point`].
+    fn constrain_corner(
+        &self,
+        point: &mut Point,
+        friction: &FrictionConfig,
+        damping: &DampingWall,
+    ) -> Vec2 {
+        let r = self.effective_corner_radius();
+        if r <= 0.0 {
+            return Vec2::ZERO;
+        }
+        let rect = self.to_rect();
+        let position = Vec2::new(point.x, point.y);
+        // Centre of the nearest corner arc, only differs from `position` on both axes in a corner
+        let center = position.clamp(rect.min + r, rect.max - r);
+        let offset = position - center;
+        if offset.x == 0.0 || offset.y == 0.0 || offset.length() <= r {
+            return Vec2::ZERO;
+        }
+
+        let normal = offset.normalize();
+        let vel = Vec2::new(point.vel_x(), point.vel_y());
+        let position = center + normal * r;
+        let normal_vel = vel.dot(normal);
+        let new_vel = if normal_vel > 0.0 {
+            let horizontal = if offset.x > 0.0 {
+                Side::Right
+            } else {
+                Side::Left
+            };
+            let vertical = if offset.y > 0.0 {
+                Side::Top
+            } else {
+                Side::Bottom
+            };
+            let corner_damping = (damping.side(horizontal) + damping.side(vertical)) / 2.0;
+            let vel_after_friction = vel * Vec2::new(friction.friction_x, friction.friction_y);
+            let normal_vel = vel_after_friction.dot(normal);
+            vel_after_friction
+                - normal * normal_vel * (1.0 + damping.bounce * (1.0 - corner_damping))
+        } else {
+            vel
+        };
+        point.x = position.x;
+        point.y = position.y;
+        point.old_x = position.x - new_vel.x;
+        point.old_y = position.y - new_vel.y;
+        point.mass * (new_vel - vel)
+    }
+
+    /// Rounds inwards so the result never reaches outside `rect`
+    fn from_rect(rect: Rect) -> Self {
+        Bounds::new(
+            rect.min.x.ceil() as i32,
+            rect.min.y.ceil() as i32,
+            rect.max.x.floor() as i32,
+            rect.max.y.floor() as i32,
+        )
+    }
+
+    fn to_rect(&self) -> Rect {
+        Rect::from_corners(
+            Vec2::new(self.min_x as f32, self.min_y as f32),
+            Vec2::new(self.max_x as f32, self.max_y as f32),
+        )
+    }
+
+    /// Grows the bounds to the smallest box that also holds every one of `points` with `margin`
+    /// to spare, rounded outwards to whole units
+    fn expand_to_fit(&mut self, points: &[Vec2], margin: f32) {
+        let rect = points.iter().fold(self.to_rect(), |rect, point| {
+            rect.union(Rect::from_center_size(*point, Vec2::splat(2.0 * margin)))
+        });
+        *self = Bounds::new(
+            rect.min.x.floor() as i32,
+            rect.min.y.floor() as i32,
+            rect.max.x.ceil() as i32,
+            rect.max.y.ceil() as i32,
+        )
+        .with_corner_radius(self.corner_radius);
+    }
+
+    /// Starts moving the walls in to the smallest box around the points, see
+    /// [`bounds_transition_system`]
+    fn shrink_to_current_particles(&self, points: &Query<&Point>) -> Option<BoundsTransition> {
+        let first = points.iter().next()?;
+        let mut target = Bounds::from_rect(Rect::from_center_size(
+            Vec2::new(first.x, first.y),
+            Vec2::ZERO,
+        ))
+        .with_corner_radius(self.corner_radius);
+        let centers: Vec<Vec2> = points
+            .iter()
+            .map(|point| Vec2::new(point.x, point.y))
+            .collect();
+        let margin = points.iter().map(|point| point.radius).fold(0.0, f32::max);
+        target.expand_to_fit(&centers, margin);
+
+        Some(BoundsTransition {
+            from: self.to_rect(),
+            to: target.to_rect(),
+            timer: Timer::from_seconds(1.0, TimerMode::Once),
+        })
+    }
+
+    /// Fraction of the point's area outside the bounds, 0 when fully inside and 1 when fully out.
+    /// Overlaps past two walls at once (in a corner) are counted twice, so this is an upper bound.
+    fn overlap_fraction(&self, point: &Point) -> f32 {
+        let rect = self.to_rect();
+        let r = point.radius;
+        if r <= 0.0 {
+            return if rect.contains(Vec2::new(point.x, point.y)) {
+                0.0
+            } else {
+                1.0
+            };
+        }
+
+        // Area of the circle on the far side of a wall `d` from its centre (negative when outside)
+        let segment = |d: f32| {
+            let d = d.clamp(-r, r);
+            r * r * (d / r).acos() - d * (r * r - d * d).sqrt()
+        };
+        let outside = segment(point.x - rect.min.x)
+            + segment(rect.max.x - point.x)
+            + segment(point.y - rect.min.y)
+            + segment(rect.max.y - point.y);
+        (outside / (std::f32::consts::PI * r * r)).clamp(0.0, 1.0)
+    }
+
+    /// Keeps the point inside the bounds and returns the impulse the wall applied along `axis`.
+    /// A point whose centre crossed a wall is snapped back onto it, one that only straddles a wall
+    /// is pushed back by a share of its overlap proportional to [`Bounds::overlap_fraction`].
+    fn constrain_point(
+        &self,
+        point: &mut Point,
+        axis: Axis,
+        friction: &FrictionConfig,
+        damping: &DampingWall,
+    ) -> f32 {
+        let axis_vel = |point: &Point| match axis {
+            Axis::Horizontal => point.vel_x(),
+            Axis::Vertical => point.vel_y(),
+        };
+        let vel_before = axis_vel(point);
+
+        match axis {
+            Axis::Horizontal => {
+                if point.x > self.max_x as f32 {
+                    let vel_x = point.vel_x() * friction.friction_x;
+                    point.x = self.max_x as f32;
+                    point.old_x = self.max_x as f32 + vel_x * damping.bounce(Side::Right);
+                } else if point.x < self.min_x as f32 {
+                    let vel_x = point.vel_x() * friction.friction_x;
+                    point.x = self.min_x as f32;
+                    point.old_x = self.min_x as f32 + vel_x * damping.bounce(Side::Left);
+                } else if point.x + point.radius > self.max_x as f32 {
+                    point.x -=
+                        (point.x + point.radius - self.max_x as f32) * self.overlap_fraction(point);
+                } else if point.x - point.radius < self.min_x as f32 {
+                    point.x += (self.min_x as f32 - (point.x - point.radius))
+                        * self.overlap_fraction(point);
+                }
+            }
+            Axis::Vertical => {
+                if point.y > self.max_y as f32 {
+                    let vel_y = point.vel_y() * friction.friction_y;
+                    point.y = self.max_y as f32;
+                    point.old_y = self.max_y as f32 + vel_y * damping.bounce(Side::Top);
+                } else if point.y < self.min_y as f32 {
+                    let vel_y = point.vel_y() * friction.friction_y;
+                    point.y = self.min_y as f32;
+                    point.old_y = self.min_y as f32 + vel_y * damping.bounce(Side::Bottom);
+                } else if point.y + point.radius > self.max_y as f32 {
+                    point.y -=
+                        (point.y + point.radius - self.max_y as f32) * self.overlap_fraction(point);
+                } else if point.y - point.radius < self.min_y as f32 {
+                    point.y += (self.min_y as f32 - (point.y - point.radius))
+                        * self.overlap_fraction(point);
+                }
+            }
+        }
+
+        point.mass * (axis_vel(point) - vel_before)
+    }
+}
+
+impl Clone for Point {
+    fn clone(&self) -> Self {
+        Self {
+            x: self.x,
+            y: self.y,
+            old_x: self.old_x,
+            old_y: self.old_y,
+            radius: self.radius,
+            mass: self.mass,
+            color: self.color,
+            id: self.id,
+            acc_x: 0.0,
+            acc_y: 0.0,
+            coordination_number: 0,
+            category: self.category,
+            temperature: self.temperature,
+            u: self.u,
+            v: self.v,
+        }
+    }
+}
+
+impl Point {
+    fn new(id: i32, x: f32, y: f32, vel_x: f32, vel_y: f32) -> Self {
+        let old_x = x - vel_x;
+        let old_y = y - vel_y;
+
+        //println!("{:?} {:?} {:?}", x, old_x, vel_x);
+
+        Point {
+            id,
+            x,
+            y,
+            old_x,
+            old_y,
+            radius: 1.0,
+            mass: 1.0,
+            color: 0,
+            acc_x: 0.0,
+            acc_y: 0.0,
+            coordination_number: 0,
+            category: 0,
+            temperature: AMBIENT_TEMPERATURE,
+            u: 1.0,
+            v: 0.0,
+        }
+    }
+
+    fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    fn with_category(mut self, category: u32) -> Self {
+        self.category = category;
+        self
+    }
+
+    fn vel_x(&self) -> f32 {
+        self.x - self.old_x
+    }
+    fn vel_y(&self) -> f32 {
+        self.y - self.old_y
+    }
+
+    /// In the same per-substep units as the velocity
+    fn momentum(&self) -> Vec2 {
+        Vec2::new(self.vel_x(), self.vel_y()) * self.mass
+    }
+
+    /// Integrates one substep and returns the wall impulse picked up while constraining
+    fn move_point(
+        &mut self,
+        bounds: &Bounds,
+        friction: &FrictionConfig,
+        damping: &DampingWall,
+        dt: f32,
+    ) -> (f32, f32) {
+        let vel_x = self.vel_x() * friction.friction_x;
+        let vel_y = self.vel_y() * friction.friction_y;
+
+        self.old_x = self.x;
+        self.old_y = self.y;
+
+        self.x += vel_x + self.acc_x * dt * dt;
+        self.y += vel_y + self.acc_y * dt * dt;
+
+        self.acc_x = 0.0;
+        self.acc_y = 0.0;
+
+        let impulse_x = bounds.constrain_point(self, Axis::Horizontal, friction, damping);
+        let impulse_y = bounds.constrain_point(self, Axis::Vertical, friction, damping);
+        let corner = bounds.constrain_corner(self, friction, damping);
+
+        //println!("{:?}", self);
+        (impulse_x + corner.x, impulse_y + corner.y)
+    }
+
+    fn apply_acceleration(&mut self, x: f32, y: f32) {
+        self.acc_x += x;
+        self.acc_y += y;
+    }
+
+    fn dist(&self, other: &Point) -> f32 {
+        let dx = self.x - other.x;
+        let dy = self.y - other.y;
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    fn colliding(&self, other: &Point) -> bool {
+        let dist = self.dist(other);
+        self.radius + other.radius > dist
+    }
+}
+
+/// How far `p1` moves to separate the pair, `p2` moves the same amount the other way
+fn collision_correction(p1: &Point, p2: &Point) -> Vec2 {
+    let delta_x = p1.x - p2.x;
+    let delta_y = p1.y - p2.y;
+
+    let dist = p1.dist(p2);
+    let n_x = delta_x / dist;
+    let n_y = delta_y / dist;
+
+    let delta = p1.radius + p2.radius - dist;
+
+    Vec2::new(0.5 * delta * n_x * FRICTION, 0.5 * delta * n_y * FRICTION)
+}
+
+fn solve_collision(p1: &mut Point, p2: &mut Point) {
+    let correction = collision_correction(p1, p2);
+
+    p1.x += correction.x;
+    p1.y += correction.y;
+    p2.x -= correction.x;
+    p2.y -= correction.y;
+}
+
+/// Two-level spatial hash for the collision broad phase. Points whose radius is large compared to
+/// the smallest one go into a coarse grid sized for the biggest point, everything else into a fine
+/// grid, so a few large points don't force huge cells onto all the small ones.
+///
+/// Every cell is at least as wide as the largest diameter in its level, so touching points are
+/// always in the same or neighbouring cells.
+struct SpatialHash {
+    cell_sizes: [f32; 2],
+    levels: [HashMap<(i32, i32), Vec<usize>>; 2],
+    /// Level of every inserted point, by index
+    point_levels: Vec<usize>,
+}
+
+impl SpatialHash {
+    const FINE: usize = 0;
+    const COARSE: usize = 1;
+
+    fn new(points: &[(Vec2, f32)]) -> Self {
+        let (min_radius, max_radius) = points
+            .iter()
+            .fold((f32::MAX, 0.0_f32), |(min, max), (_, r)| {
+                (min.min(*r), max.max(*r))
+            });
+        // Anything up to twice the smallest radius counts as small
+        let split = 2.0 * min_radius;
+        let fine_size = 2.0 * split.min(max_radius);
+
+        let mut hash = SpatialHash {
+            cell_sizes: [fine_size, 2.0 * max_radius],
+            levels: default(),
+            point_levels: Vec::with_capacity(points.len()),
+        };
+        for (index, (position, radius)) in points.iter().enumerate() {
+            let level = if *radius <= split {
+                Self::FINE
+            } else {
+                Self::COARSE
+            };
+            let cell = hash.cell(level, *position);
+            hash.levels[level].entry(cell).or_default().push(index);
+            hash.point_levels.push(level);
+        }
+        hash
+    }
+
+    fn cell(&self, level: usize, position: Vec2) -> (i32, i32) {
+        let size = self.cell_sizes[level];
+        (
+            (position.x / size).floor() as i32,
+            (position.y / size).floor() as i32,
+        )
+    }
+
+    fn neighbours(&self, level: usize, position: Vec2) -> impl Iterator<Item = usize> + '_ {
+        let (cx, cy) = self.cell(level, position);
+        (-1..=1)
+            .flat_map(move |dx| (-1..=1).map(move |dy| (cx + dx, cy + dy)))
+            .filter_map(move |cell| self.levels[level].get(&cell))
+            .flatten()
+            .copied()
+    }
+
+    /// Every pair that might be touching, each pair only once
+    fn candidate_pairs(&self, points: &[(Vec2, f32)]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for (index, (position, _)) in points.iter().enumerate() {
+            let level = self.point_levels[index];
+            pairs.extend(
+                self.neighbours(level, *position)
+                    .filter(|other| *other > index)
+                    .map(|other| (index, other)),
+            );
+
+            // Small points look up the large ones, never the other way around
+            if level == Self::FINE {
+                pairs.extend(
+                    self.neighbours(Self::COARSE, *position)
+                        .map(|other| (index, other)),
+                );
+            }
+        }
+        pairs
+    }
+}
+
+const GRAVITY: f32 = -100.0;
+const FRICTION: f32 = 0.99;
+const BOUNCE: f32 = 0.99;
+const SUBSTEPS: u8 = 8;
+const MAX_COLLISIONS_PER_SUBSTEP: u32 = 10_000;
+/// Discrepancy the momentum check lets through. Momentum should be conserved exactly, so this
+/// only absorbs `f32` rounding and is not a setting.
+const MOMENTUM_TOLERANCE: f32 = 0.001;
+/// How long a spawned or teleported point ignores collisions. Only has to outlast the few frames
+/// it takes to push out of whatever the point landed in, so it is not a setting either.
+const IMMUNITY_SECONDS: f32 = 0.1;
+
+const GAME_SCALE: f32 = 20.0;
+
+const SPAWN_Y: f32 = 20.0;
+/// Temperature of every new point, colors run from blue at 0 to red at 1
+const AMBIENT_TEMPERATURE: f32 = 0.5;
+const FLIGHT_MEAN_WINDOW: usize = 20;
+/// Speed of the points spawned with [`KeyBindings::arc_speed`], in units per second
+const ARC_SPEED: f32 = 15.0;
+const IMAGE_PATH: &str = "assets/particles.png";
+const IMAGE_POINTS: usize = 400;
+/// Frames of total momentum kept for the plot
+const MOMENTUM_HISTORY: usize = 300;
+const MOMENTUM_PLOT_HEIGHT: f32 = 4.0;
+/// Segregation index reports shown in the Brazil nut plot, one per second
+const SEGREGATION_HISTORY: usize = 120;
+const SEGREGATION_PLOT_HEIGHT: f32 = 8.0;
+/// The momentum arrow is as long as the centre of mass travels in this time
+const MOMENTUM_ARROW_SECONDS: f32 = 0.5;
+const MAZE_PATH: &str = "assets/maze.png";
+/// Loaded through the asset server, so relative to `assets/`
+const HUD_FONT_PATH: &str = "fonts/DejaVuSansMono.ttf";
+/// Distance of the pressure drive's inlet and outlet from the side walls
+const CHANNEL_MARGIN: f32 = 2.0;
+/// Horizontal slices the flow profile is averaged over
+const PROFILE_BINS: usize = 8;
+/// How long the compression peak is tracked before the wave speed is reported
+const WAVE_MEASURE_SECONDS: f32 = 3.0;
+const TILT_RUNS: usize = 10;
+/// Relative jump in the spread of point heights between two samples that counts as an avalanche
+const AVALANCHE_THRESHOLD: f32 = 0.05;
+/// Points slower than this, in units per second, count as part of the resting pile
+const SETTLED_SPEED: f32 = 2.0;
+const PHASE_SECONDS: f32 = 5.0;
+/// Fraction of the bounds height left at the end of the compress phase
+const COMPRESSED_HEIGHT: f32 = 0.5;
+const BURST_SPEED: f32 = 0.05;
+const SPAWN_CONFIG_PATH: &str = "assets/spawner.json";
+
+/// Thomas Wang's 64 bit integer hash, consecutive inputs end up far apart
+fn wang_hash(mut key: u64) -> u64 {
+    key = (!key).wrapping_add(key << 21);
+    key ^= key >> 24;
+    key = key.wrapping_add(key << 3).wrapping_add(key << 8);
+    key ^= key >> 14;
+    key = key.wrapping_add(key << 2).wrapping_add(key << 4);
+    key ^= key >> 28;
+    key.wrapping_add(key << 31)
+}
+
+/// Picks a color from the whole RGB cube, so neighbouring ids look nothing alike
+fn id_to_color(id: u64) -> Color {
+    let [red, green, blue, ..] = wang_hash(id).to_le_bytes();
+    Color::rgb_u8(red, green, blue)
+}
+
+/// Viridis sampled at 7 steps, indexed by coordination number (6 = hexagonal close packing)
+const COORDINATION_COLORS: [Color; 7] = [
+    Color::rgb(0.267, 0.004, 0.329),
+    Color::rgb(0.267, 0.224, 0.514),
+    Color::rgb(0.192, 0.408, 0.557),
+    Color::rgb(0.129, 0.569, 0.549),
+    Color::rgb(0.208, 0.718, 0.475),
+    Color::rgb(0.565, 0.843, 0.263),
+    Color::rgb(0.992, 0.906, 0.145),
+];
+
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+enum ColorMode {
+    #[default]
+    Id,
+    Coordination,
+    Temperature,
+    Reaction,
+}
+
+impl ColorMode {
+    fn next(self) -> Self {
+        match self {
+            ColorMode::Id => ColorMode::Coordination,
+            ColorMode::Coordination => ColorMode::Temperature,
+            ColorMode::Temperature => ColorMode::Reaction,
+            ColorMode::Reaction => ColorMode::Id,
+        }
+    }
+
+    fn color(self, point: &Point) -> Color {
+        match self {
+            ColorMode::Id => id_to_color(point.id as u64),
+            ColorMode::Coordination => {
+                COORDINATION_COLORS[(point.coordination_number as usize).min(6)]
+            }
+            ColorMode::Temperature => {
+                let t = point.temperature.clamp(0.0, 1.0);
+                Color::rgb(t, 0.0, 1.0 - t)
+            }
+            ColorMode::Reaction => {
+                let t = ((point.u - point.v + 1.0) / 2.0).clamp(0.0, 1.0);
+                COORDINATION_COLORS[(t * 6.0).round() as usize]
+            }
+        }
+    }
+}
+
+/// Speed, in units per substep, above which points start to glow
+#[cfg(feature = "bloom")]
+const GLOW_SPEED: f32 = 0.1;
+
+/// HDR multiplier for a point's color, anything above 1.0 shows up in the bloom. Points glow
+/// once they are faster than [`GLOW_SPEED`] or warmer than [`AMBIENT_TEMPERATURE`], a point at
+/// temperature 1.0 glows twice as bright.
+#[cfg(feature = "bloom")]
+fn bloom_intensity(speed: f32, temp: f32) -> f32 {
+    1.0 + (speed / GLOW_SPEED - 1.0).max(0.0)
+        + (temp - AMBIENT_TEMPERATURE).max(0.0) / (1.0 - AMBIENT_TEMPERATURE)
+}
+
+fn update_color_system(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut mode: ResMut<ColorMode>,
+    mut query: Query<(&Point, &mut DrawMode)>,
+) {
+    if keys.just_pressed(bindings.color_mode) {
+        *mode = mode.next();
+        info!("Coloring by {:?}", *mode);
+    }
+
+    for (point, mut draw_mode) in query.iter_mut() {
+        let color = mode.color(point);
+        #[cfg(feature = "bloom")]
+        let color = {
+            let speed = Vec2::new(point.vel_x(), point.vel_y()).length();
+            color * bloom_intensity(speed, point.temperature)
+        };
+        // Only flag real changes, lyon rebuilds the mesh of every changed DrawMode
+        if let DrawMode::Outlined { fill_mode, .. } = draw_mode.bypass_change_detection() {
+            if fill_mode.color != color {
+                fill_mode.color = color;
+                draw_mode.set_changed();
+            }
+        }
+    }
+}
+
+fn create_sprite(radius: f32, id: i32) -> ShapeBundle {
+    let shape = shapes::RegularPolygon {
+        sides: 24,
+        feature: shapes::RegularPolygonFeature::Radius(radius),
+        ..shapes::RegularPolygon::default()
+    };
+
+    GeometryBuilder::build_as(
+        &shape,
+        DrawMode::Outlined {
+            fill_mode: FillMode::color(id_to_color(id as u64)),
+            outline_mode: StrokeMode::new(Color::BLACK, 0.2),
+        },
+        Transform {
+            translation: Vec3::new(100_100.0, 100_100.0, 100_100.0),
+            scale: Vec3::new(GAME_SCALE, GAME_SCALE, GAME_SCALE),
+            ..default()
+        },
+    )
+}
+
+/// Points further than `full_detail_radius` from the camera are drawn as sprite quads, which
+/// Bevy batches into a single draw, instead of a 24-gon lyon mesh each
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct RenderDistance {
+    full_detail_radius: f32,
+}
+
+impl Default for RenderDistance {
+    fn default() -> Self {
+        RenderDistance {
+            full_detail_radius: 40.0,
+        }
+    }
+}
+
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct HighDetailRender;
+
+type LodQuery<'a> = (
+    Entity,
+    &'a Point,
+    &'a mut DrawMode,
+    Option<&'a mut Sprite>,
+    Option<&'a HighDetailRender>,
+);
+
+fn lod_render_system(
+    mut commands: Commands,
+    render_distance: Res<RenderDistance>,
+    camera: Query<&GlobalTransform, With<Camera>>,
+    mut points: Query<LodQuery>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    let camera = camera.translation().truncate() / GAME_SCALE;
+
+    for (entity, point, mut draw_mode, sprite, high_detail) in points.iter_mut() {
+        let near =
+            Vec2::new(point.x, point.y).distance(camera) < render_distance.full_detail_radius;
+        let color = match draw_mode.as_ref() {
+            DrawMode::Outlined { fill_mode, .. } | DrawMode::Fill(fill_mode) => fill_mode.color,
+            DrawMode::Stroke(stroke_mode) => stroke_mode.color,
+        };
+
+        match (near, sprite) {
+            (true, Some(_)) => {
+                commands
+                    .entity(entity)
+                    .remove::<(Sprite, Handle<Image>)>()
+                    .insert((HighDetailRender, Mesh2dHandle::default()));
+                // Get lyon to tessellate a fresh mesh for the new handle
+                draw_mode.set_changed();
+            }
+            (true, None) => {
+                if high_detail.is_none() {
+                    commands.entity(entity).insert(HighDetailRender);
+                }
+            }
+            (false, Some(mut sprite)) => {
+                if sprite.color != color {
+                    sprite.color = color;
+                }
+            }
+            (false, None) => {
+                commands
+                    .entity(entity)
+                    .remove::<(HighDetailRender, Mesh2dHandle)>()
+                    .insert((
+                        Sprite {
+                            color,
+                            custom_size: Some(Vec2::splat(point.radius * 2.0)),
+                            ..default()
+                        },
+                        DEFAULT_IMAGE_HANDLE.typed::<Image>(),
+                    ));
+            }
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ShadowConfig {
+    offset: Vec2,
+    opacity: f32,
+    blur: f32,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        ShadowConfig {
+            offset: Vec2::new(0.3, -0.3),
+            opacity: 0.3,
+            blur: 0.0,
+        }
+    }
+}
+
+/// Drop shadow drawn behind the `owner` point
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct ShadowEntity {
+    owner: Entity,
+}
+
+// Only there so reflection can build one, `update_shadows` despawns shadows without a live owner
+impl FromWorld for ShadowEntity {
+    fn from_world(_world: &mut World) -> Self {
+        ShadowEntity {
+            owner: Entity::from_raw(u32::MAX),
+        }
+    }
+}
+
+fn create_shadow_sprite(radius: f32, config: &ShadowConfig) -> ShapeBundle {
+    // A blurred shadow is a bit larger and coarser, close enough to a soft edge at this size
+    let (sides, radius) = if config.blur > 0.0 {
+        (8, radius * (1.0 + config.blur))
+    } else {
+        (24, radius)
+    };
+    let shape = shapes::RegularPolygon {
+        sides,
+        feature: shapes::RegularPolygonFeature::Radius(radius),
+        ..shapes::RegularPolygon::default()
+    };
+
+    GeometryBuilder::build_as(
+        &shape,
+        DrawMode::Fill(FillMode::color(Color::rgba(0.0, 0.0, 0.0, config.opacity))),
+        Transform {
+            translation: Vec3::new(100_100.0, 100_100.0, 100_100.0),
+            scale: Vec3::new(GAME_SCALE, GAME_SCALE, GAME_SCALE),
+            ..default()
+        },
+    )
+}
+
+fn spawn_shadows(
+    mut commands: Commands,
+    config: Option<Res<ShadowConfig>>,
+    query: Query<(Entity, &Point), Added<Point>>,
+) {
+    let Some(config) = config else {
+        return;
+    };
+
+    for (owner, point) in query.iter() {
+        commands.spawn((
+            create_shadow_sprite(point.radius, &config),
+            ShadowEntity { owner },
+        ));
+    }
+}
+
+fn update_shadows(
+    mut commands: Commands,
+    config: Option<Res<ShadowConfig>>,
+    mut shadows: Query<(Entity, &ShadowEntity, &mut Transform)>,
+    points: Query<&Point>,
+) {
+    for (entity, shadow, mut transform) in shadows.iter_mut() {
+        match (&config, points.get(shadow.owner)) {
+            (Some(config), Ok(point)) => {
+                transform.translation = Vec3::new(
+                    (point.x + config.offset.x) * GAME_SCALE,
+                    (point.y + config.offset.y) * GAME_SCALE,
+                    -1.0,
+                );
+            }
+            _ => commands.entity(entity).despawn(),
+        }
+    }
+}
+
+/// Spawns the point together with everything a freshly spawned point carries
+fn spawn_point(commands: &mut Commands, point: Point, time: f32) -> Entity {
+    commands
+        .spawn((
+            create_sprite(point.radius, point.id),
+            FlightTimer::start(&point, time),
+            point,
+            Immune::new(),
+        ))
+        .id()
+}
+
+/// Keeps other points from pushing this one around for a moment, so a point that appears on top
+/// of others doesn't get flung out. It still pushes them and still hits the walls.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct Immune {
+    duration: f32,
+}
+
+impl Immune {
+    fn new() -> Self {
+        Immune {
+            duration: IMMUNITY_SECONDS,
+        }
+    }
+}
+
+fn immune_system(mut commands: Commands, time: Res<Time>, mut query: Query<(Entity, &mut Immune)>) {
+    for (entity, mut immune) in query.iter_mut() {
+        immune.duration -= time.delta_seconds();
+        if immune.duration <= 0.0 {
+            commands.entity(entity).remove::<Immune>();
+        }
+    }
+}
+
+fn add_points(mut commands: Commands) {
+    spawn_point(&mut commands, Point::new(0, 5.0, 20.0, 0.1, 0.0), 0.0);
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct GravityConfig {
+    x: f32,
+    y: f32,
+}
+
+impl Default for GravityConfig {
+    fn default() -> Self {
+        GravityConfig { x: 0.0, y: GRAVITY }
+    }
+}
+
+/// Scales gravity per point category, categories without an entry get the full gravity
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct CategoryGravity {
+    multipliers: HashMap<u32, f32>,
+}
+
+impl Default for CategoryGravity {
+    fn default() -> Self {
+        CategoryGravity {
+            multipliers: HashMap::from_iter([(1, 0.5)]),
+        }
+    }
+}
+
+/// How much of the bounce each wall swallows, indexed by [`Side`]. 0 reflects like before, 1
+/// stops the point dead against the wall
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct DampingWall {
+    sides: [f32; 4],
+    /// Fraction of the speed into a wall that an undamped wall sends back
+    bounce: f32,
+}
+
+impl Default for DampingWall {
+    fn default() -> Self {
+        DampingWall {
+            sides: [0.0; 4],
+            bounce: BOUNCE,
+        }
+    }
+}
+
+impl DampingWall {
+    fn side(&self, side: Side) -> f32 {
+        self.sides[side as usize].clamp(0.0, 1.0)
+    }
+
+    /// What is left of [`DampingWall::bounce`] after the `side`'s damping
+    fn bounce(&self, side: Side) -> f32 {
+        self.bounce * (1.0 - self.side(side))
+    }
+}
+
+/// Velocity kept per substep on each axis, e.g. a low `friction_x` loss with normal `friction_y`
+/// drag behaves like a slippery floor
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct FrictionConfig {
+    friction_x: f32,
+    friction_y: f32,
+}
+
+impl Default for FrictionConfig {
+    fn default() -> Self {
+        FrictionConfig {
+            friction_x: FRICTION,
+            friction_y: FRICTION,
+        }
+    }
+}
+
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+struct CollisionBudget {
+    max_per_substep: u32,
+    exceeded_frames: u64,
+}
+
+/// Momentum bookkeeping for one frame of physics. `px`/`py` is the total momentum before the
+/// substeps run; every velocity change that isn't a collision is accumulated as an impulse, so
+/// collisions alone must account for any difference at the end of the frame.
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+struct MomentumTracker {
+    px: f32,
+    py: f32,
+    wall_impulse_x: f32,
+    wall_impulse_y: f32,
+    body_impulse_x: f32,
+    body_impulse_y: f32,
+}
+
+/// Continuous pair potential `V(r) = 4ε[(σ/r)¹² - (σ/r)⁶]` that replaces the hard-sphere contacts
+/// while enabled. Repulsive below `2^(1/6) σ`, attractive beyond it, ignored past `cutoff`.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct LennardJones {
+    enabled: bool,
+    epsilon: f32,
+    sigma: f32,
+    cutoff: f32,
+}
+
+impl Default for LennardJones {
+    fn default() -> Self {
+        // Puts the bottom of the well at the contact distance of two unit points
+        let sigma = 2.0 / 2.0_f32.powf(1.0 / 6.0);
+        LennardJones {
+            enabled: false,
+            epsilon: 20.0,
+            sigma,
+            cutoff: 2.5 * sigma,
+        }
+    }
+}
+
+impl LennardJones {
+    /// `F(r) = -dV/dr = 24ε/r [2(σ/r)¹² - (σ/r)⁶]`, positive pushes apart
+    fn force(&self, r: f32) -> f32 {
+        // Very close pairs would get a force big enough to launch them out of the bounds
+        let r = r.max(0.8 * self.sigma);
+        let s6 = (self.sigma / r).powi(6);
+        24.0 * self.epsilon / r * (2.0 * s6 * s6 - s6)
+    }
+
+    /// Acceleration on every point from all its neighbours within the cutoff
+    fn accelerations(&self, points: &[(Vec2, f32)]) -> Vec<Vec2> {
+        // A radius of half the cutoff makes every pair within the cutoff a candidate
+        let shapes: Vec<_> = points
+            .iter()
+            .map(|(position, _)| (*position, self.cutoff / 2.0))
+            .collect();
+        let hash = SpatialHash::new(&shapes);
+
+        let mut accelerations = vec![Vec2::ZERO; points.len()];
+        for (a, b) in hash.candidate_pairs(&shapes) {
+            let ((pa, ma), (pb, mb)) = (points[a], points[b]);
+            let delta = pa - pb;
+            let r = delta.length();
+            if r >= self.cutoff || r <= 0.0 {
+                continue;
+            }
+            let force = delta / r * self.force(r);
+            accelerations[a] += force / ma;
+            accelerations[b] -= force / mb;
+        }
+        accelerations
+    }
+}
+
+fn lennard_jones_toggle_system(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut lennard_jones: ResMut<LennardJones>,
+) {
+    if keys.just_pressed(bindings.lennard_jones) {
+        lennard_jones.enabled = !lennard_jones.enabled;
+        info!(
+            "Lennard-Jones potential {}",
+            if lennard_jones.enabled { "on" } else { "off" }
+        );
+    }
+}
+
+fn total_momentum<'a>(points: impl Iterator<Item = &'a Point>) -> (f32, f32) {
+    let p: Vec2 = points.map(Point::momentum).sum();
+    (p.x, p.y)
+}
+
+type PhysicsItem = (Entity, &'static mut Point);
+
+/// Redoes substeps that pump energy into the system, e.g. from deep overlaps being pushed apart,
+/// as two steps of half the length, halving again if needed. Falling points trade potential for
+/// kinetic energy, so the check is on the mechanical energy.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct AdaptiveTimestep {
+    enabled: bool,
+    /// Largest allowed energy gain in one substep, as a fraction of the kinetic energy
+    max_energy_change: f32,
+    max_retries: u8,
+    /// Substeps that had to run with a shorter step so far
+    shortened_substeps: u64,
+}
+
+impl Default for AdaptiveTimestep {
+    fn default() -> Self {
+        AdaptiveTimestep {
+            enabled: false,
+            max_energy_change: 0.5,
+            max_retries: 4,
+            shortened_substeps: 0,
+        }
+    }
+}
+
+/// Kinetic energy below which a gain is not worth retrying, per point (a speed of 1 unit/s)
+const ENERGY_FLOOR: f32 = 0.5;
+
+/// Kinetic plus gravitational potential energy, and the kinetic part alone
+fn mechanical_energy(
+    query: &Query<PhysicsItem>,
+    sub_dt: f32,
+    gravity: &GravityConfig,
+    category_gravity: &CategoryGravity,
+    drive: f32,
+) -> (f32, f32) {
+    query
+        .iter()
+        .fold((0.0, 0.0), |(total, kinetic), (_, point)| {
+            let vel = Vec2::new(point.vel_x(), point.vel_y()) / sub_dt;
+            let multiplier = category_gravity
+                .multipliers
+                .get(&point.category)
+                .copied()
+                .unwrap_or(1.0);
+            let g = Vec2::new(gravity.x, gravity.y) * multiplier;
+            let k = 0.5 * point.mass * vel.length_squared();
+            let u = -point.mass * (g.dot(Vec2::new(point.x, point.y)) + drive * point.x);
+            (total + k + u, kinetic + k)
+        })
+}
+
+/// Stretches the per-substep velocity of every point for a substep `factor` times as long
+fn rescale_velocities(query: &mut Query<PhysicsItem>, factor: f32) {
+    if factor == 1.0 {
+        return;
+    }
+    for (_, mut point) in query.iter_mut() {
+        point.old_x = point.x - point.vel_x() * factor;
+        point.old_y = point.y - point.vel_y() * factor;
+    }
+}
+
+/// What one substep did to the momentum bookkeeping, and whether it ran out of collision budget
+#[derive(Default)]
+struct SubstepResult {
+    wall_impulse: Vec2,
+    /// Gravity, friction, tethers, speed constraints and pushes from [`Immune`] points
+    body_impulse: Vec2,
+    /// Position and impulse of every point that hit a wall
+    wall_hits: Vec<(Vec2, Vec2)>,
+    /// Points touching the right wall at the end of the substep
+    right_wall_contacts: Vec<Entity>,
+    budget_exceeded: bool,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn substep(
+    query: &mut Query<PhysicsItem>,
+    sub_dt: f32,
+    bounds: &Bounds,
+    gravity: &GravityConfig,
+    category_gravity: &CategoryGravity,
+    drive: f32,
+    lennard_jones: &LennardJones,
+    tethers: &[&Tether],
+    arc_speeds: &[&ArcSpeedConstraint],
+    friction: &FrictionConfig,
+    damping: &DampingWall,
+    immune: &HashSet<Entity>,
+    max_collisions: u32,
+) -> SubstepResult {
+    let mut result = SubstepResult::default();
+    for tether in tethers {
+        if let Ok((_, mut point)) = query.get_mut(tether.point) {
+            let acceleration = tether.acceleration(&point);
+            point.apply_acceleration(acceleration.x, acceleration.y);
+        }
+    }
+
+    let lj_accelerations = lennard_jones.enabled.then(|| {
+        let points: Vec<_> = query
+            .iter()
+            .map(|(_, point)| (Vec2::new(point.x, point.y), point.mass))
+            .collect();
+        lennard_jones.accelerations(&points)
+    });
+
+    for (index, (entity, mut point)) in query.iter_mut().enumerate() {
+        let (vel_x, vel_y) = (point.vel_x(), point.vel_y());
+
+        if let Some(accelerations) = &lj_accelerations {
+            point.apply_acceleration(accelerations[index].x, accelerations[index].y);
+        }
+        let multiplier = category_gravity
+            .multipliers
+            .get(&point.category)
+            .copied()
+            .unwrap_or(1.0);
+        point.apply_acceleration(gravity.x * multiplier + drive, gravity.y * multiplier);
+        let (wall_x, wall_y) = point.move_point(bounds, friction, damping, sub_dt);
+
+        let wall = Vec2::new(wall_x, wall_y);
+        if wall != Vec2::ZERO {
+            result.wall_hits.push((Vec2::new(point.x, point.y), wall));
+        }
+        if point.x + point.radius >= bounds.max_x as f32 {
+            result.right_wall_contacts.push(entity);
+        }
+        result.wall_impulse += wall;
+        // Gravity and friction, i.e. whatever changed the velocity besides the walls
+        result.body_impulse +=
+            point.mass * Vec2::new(point.vel_x() - vel_x, point.vel_y() - vel_y) - wall;
+    }
+
+    // The potential keeps the points apart on its own
+    if lennard_jones.enabled {
+        apply_arc_speeds(query, arc_speeds, sub_dt, &mut result);
+        return result;
+    }
+
+    let (entities, shapes): (Vec<_>, Vec<_>) = query
+        .iter()
+        .map(|(entity, point)| (entity, (Vec2::new(point.x, point.y), point.radius)))
+        .unzip();
+    let hash = SpatialHash::new(&shapes);
+    for (_, mut point) in query.iter_mut() {
+        point.coordination_number = 0;
+    }
+
+    let mut collisions = 0;
+    for (a, b) in hash.candidate_pairs(&shapes) {
+        let Ok([(e1, mut p1), (e2, mut p2)]) = query.get_many_mut([entities[a], entities[b]])
+        else {
+            continue;
+        };
+        if p1.colliding(p2.as_ref()) {
+            match (immune.contains(&e1), immune.contains(&e2)) {
+                (true, true) => {}
+                // The other point takes the whole correction, as if it hit a wall, so the
+                // momentum it picks up comes from outside like a wall impulse does
+                (true, false) => {
+                    let correction = collision_correction(&p1, &p2);
+                    p2.x -= 2.0 * correction.x;
+                    p2.y -= 2.0 * correction.y;
+                    result.body_impulse -= p2.mass * 2.0 * correction;
+                }
+                (false, true) => {
+                    let correction = collision_correction(&p1, &p2);
+                    p1.x += 2.0 * correction.x;
+                    p1.y += 2.0 * correction.y;
+                    result.body_impulse += p1.mass * 2.0 * correction;
+                }
+                (false, false) => solve_collision(p1.as_mut(), p2.as_mut()),
+            }
+            p1.coordination_number = p1.coordination_number.saturating_add(1);
+            p2.coordination_number = p2.coordination_number.saturating_add(1);
+
+            // Safety valve: leave the rest of this substep unresolved rather than stall the frame
+            collisions += 1;
+            if collisions >= max_collisions {
+                result.budget_exceeded = true;
+                break;
+            }
+        }
+    }
+    apply_arc_speeds(query, arc_speeds, sub_dt, &mut result);
+    result
+}
+
+/// Sets the speed of every constrained point once the walls and contacts are done with it. Points
+/// that are standing still have no direction to speed up in and are left alone.
+fn apply_arc_speeds(
+    query: &mut Query<PhysicsItem>,
+    arc_speeds: &[&ArcSpeedConstraint],
+    sub_dt: f32,
+    result: &mut SubstepResult,
+) {
+    for constraint in arc_speeds {
+        let Ok((_, mut point)) = query.get_mut(constraint.entity) else {
+            continue;
+        };
+        let vel = Vec2::new(point.vel_x(), point.vel_y());
+        let speed = vel.length();
+        if speed <= f32::EPSILON {
+            continue;
+        }
+        let new_vel = vel / speed * constraint.target_speed.max(0.0) * sub_dt;
+        point.old_x = point.x - new_vel.x;
+        point.old_y = point.y - new_vel.y;
+        result.body_impulse += point.mass * (new_vel - vel);
+    }
+}
+
+/// Everything a substep changes, so it can be rolled back
+struct Snapshot {
+    points: Vec<[f32; 4]>,
+}
+
+impl Snapshot {
+    fn take(query: &Query<PhysicsItem>) -> Self {
+        let points = query
+            .iter()
+            .map(|(_, point)| [point.x, point.y, point.old_x, point.old_y])
+            .collect();
+        Snapshot { points }
+    }
+
+    fn restore(&self, query: &mut Query<PhysicsItem>) {
+        for ((_, mut point), [x, y, old_x, old_y]) in query.iter_mut().zip(&self.points) {
+            point.x = *x;
+            point.y = *y;
+            point.old_x = *old_x;
+            point.old_y = *old_y;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_points_system(
+    mut query: Query<PhysicsItem>,
+    time: Res<Time>,
+    bounds: Res<Bounds>,
+    (gravity, category_gravity, drive): (
+        Res<GravityConfig>,
+        Res<CategoryGravity>,
+        Res<PressureDrive>,
+    ),
+    lennard_jones: Res<LennardJones>,
+    tethers: Query<&Tether>,
+    arc_speeds: Query<&ArcSpeedConstraint>,
+    friction: Res<FrictionConfig>,
+    damping: Res<DampingWall>,
+    immune: Query<Entity, With<Immune>>,
+    mut adaptive: ResMut<AdaptiveTimestep>,
+    mut heat_map: ResMut<WallHeatMap>,
+    mut budget: ResMut<CollisionBudget>,
+    mut momentum: ResMut<MomentumTracker>,
+    mut right_wall: ResMut<RightWallContacts>,
+) {
+    right_wall.entities.clear();
+    let (px, py) = total_momentum(query.iter().map(|(_, point)| point));
+    *momentum = MomentumTracker {
+        px,
+        py,
+        ..default()
+    };
+
+    let tethers: Vec<_> = tethers.iter().collect();
+    let arc_speeds: Vec<_> = arc_speeds.iter().collect();
+    let immune: HashSet<_> = immune.iter().collect();
+    let drive = drive.current_acceleration(&bounds);
+    let sub_dt = time.delta_seconds() / (SUBSTEPS as f32);
+    let mut exceeded = false;
+    for _ in 0..SUBSTEPS {
+        let before = (adaptive.enabled && sub_dt > 0.0).then(|| {
+            (
+                Snapshot::take(&query),
+                mechanical_energy(&query, sub_dt, &gravity, &category_gravity, drive),
+            )
+        });
+
+        // Friction is a loss per substep, a substep `factor` as long only loses its share of it
+        let run = |query: &mut Query<PhysicsItem>, factor: f32| {
+            let friction = FrictionConfig {
+                friction_x: friction.friction_x.powf(factor),
+                friction_y: friction.friction_y.powf(factor),
+            };
+            substep(
+                query,
+                sub_dt * factor,
+                &bounds,
+                &gravity,
+                &category_gravity,
+                drive,
+                &lennard_jones,
+                &tethers,
+                &arc_speeds,
+                &friction,
+                &damping,
+                &immune,
+                budget.max_per_substep,
+            )
+        };
+        let mut factor = 1.0;
+        let first = loop {
+            rescale_velocities(&mut query, factor);
+            let result = run(&mut query, factor);
+            rescale_velocities(&mut query, 1.0 / factor);
+
+            let Some((snapshot, (energy, kinetic))) = &before else {
+                break result;
+            };
+            let (energy_after, _) =
+                mechanical_energy(&query, sub_dt, &gravity, &category_gravity, drive);
+            let allowed = adaptive.max_energy_change
+                * kinetic.max(ENERGY_FLOOR * snapshot.points.len() as f32);
+            if energy_after - energy <= allowed {
+                break result;
+            }
+            if factor <= 0.5_f32.powi(adaptive.max_retries as i32) {
+                warn!(
+                    "Energy still grew by {:.1} after {} halvings of the substep",
+                    energy_after - energy,
+                    adaptive.max_retries
+                );
+                break result;
+            }
+            snapshot.restore(&mut query);
+            factor *= 0.5;
+        };
+
+        // A shortened substep is repeated until it has covered the whole substep, the repeats
+        // aren't checked again
+        let mut results = vec![first];
+        if factor < 1.0 {
+            adaptive.shortened_substeps += 1;
+            rescale_velocities(&mut query, factor);
+            for _ in 1..(1.0 / factor).round() as u32 {
+                results.push(run(&mut query, factor));
+            }
+            rescale_velocities(&mut query, 1.0 / factor);
+        }
+
+        for result in results {
+            // Impulses of a shortened substep are in its shorter velocity units
+            momentum.wall_impulse_x += result.wall_impulse.x / factor;
+            momentum.wall_impulse_y += result.wall_impulse.y / factor;
+            momentum.body_impulse_x += result.body_impulse.x / factor;
+            momentum.body_impulse_y += result.body_impulse.y / factor;
+            exceeded |= result.budget_exceeded;
+            for (position, impulse) in result.wall_hits {
+                heat_map.add_impact(&bounds, position, impulse / factor);
+            }
+            right_wall.entities.extend(result.right_wall_contacts);
+        }
+    }
+
+    if exceeded {
+        budget.exceeded_frames += 1;
+        warn!(
+            "Collision budget of {} per substep exceeded ({} frames so far)",
+            budget.max_per_substep, budget.exceeded_frames
+        );
+    }
+}
+
+fn momentum_check_system(query: Query<&Point>, momentum: Res<MomentumTracker>) {
+    let (px, py) = total_momentum(query.iter());
+    let expected_x = momentum.px + momentum.wall_impulse_x + momentum.body_impulse_x;
+    let expected_y = momentum.py + momentum.wall_impulse_y + momentum.body_impulse_y;
+
+    let dx = px - expected_x;
+    let dy = py - expected_y;
+    let discrepancy = (dx * dx + dy * dy).sqrt();
+    if discrepancy > MOMENTUM_TOLERANCE {
+        warn!(
+            "Momentum not conserved: expected ({}, {}) got ({}, {})",
+            expected_x, expected_y, px, py
+        );
+    }
+}
+
+/// Total momentum of all points and how its size developed over the last
+/// [`MOMENTUM_HISTORY`] frames. Collisions between points leave it alone, so the plot should only
+/// change through gravity, the walls and friction, a jump is a pair that didn't push back equally.
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+struct SystemMomentum {
+    p: Vec2,
+    // The editor can't step through a `VecDeque`
+    #[reflect(ignore)]
+    history: VecDeque<Vec2>,
+    /// Draws the arrow and plot, toggled with [`KeyBindings::momentum`]
+    show: bool,
+}
+
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct MomentumArrow;
+
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct MomentumPlot;
+
+fn spawn_momentum_display(mut commands: Commands) {
+    let hidden = |mut bundle: ShapeBundle| {
+        bundle.visibility.is_visible = false;
+        bundle
+    };
+    commands.spawn((
+        hidden(GeometryBuilder::build_as(
+            &PathBuilder::new().build(),
+            DrawMode::Stroke(StrokeMode::new(Color::YELLOW, 2.0)),
+            Transform::from_xyz(0.0, 0.0, 2.0),
+        )),
+        MomentumArrow,
+    ));
+    commands.spawn((
+        hidden(GeometryBuilder::build_as(
+            &PathBuilder::new().build(),
+            DrawMode::Stroke(StrokeMode::new(Color::YELLOW, 1.0)),
+            Transform::from_xyz(0.0, 0.0, 2.0),
+        )),
+        MomentumPlot,
+    ));
+}
+
+#[allow(clippy::type_complexity)]
+#[allow(clippy::too_many_arguments)]
+fn system_momentum_system(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    bounds: Res<Bounds>,
+    points: Query<&Point>,
+    mut momentum: ResMut<SystemMomentum>,
+    mut arrow: Query<(&mut Path, &mut Visibility), (With<MomentumArrow>, Without<MomentumPlot>)>,
+    mut plot: Query<(&mut Path, &mut Visibility), (With<MomentumPlot>, Without<MomentumArrow>)>,
+) {
+    if keys.just_pressed(bindings.momentum) {
+        momentum.show = !momentum.show;
+    }
+
+    momentum.p = points.iter().map(Point::momentum).sum();
+    let p = momentum.p;
+    momentum.history.push_back(p);
+    while momentum.history.len() > MOMENTUM_HISTORY {
+        momentum.history.pop_front();
+    }
+
+    for (_, mut visibility) in arrow.iter_mut().chain(plot.iter_mut()) {
+        visibility.is_visible = momentum.show;
+    }
+    if !momentum.show {
+        return;
+    }
+
+    // Arrow from the centre of mass to where it is heading
+    let (mass, weighted) = points.iter().fold((0.0, Vec2::ZERO), |(mass, sum), point| {
+        (
+            mass + point.mass,
+            sum + Vec2::new(point.x, point.y) * point.mass,
+        )
+    });
+    let sub_dt = time.delta_seconds() / SUBSTEPS as f32;
+    if let Ok((mut path, _)) = arrow.get_single_mut() {
+        let mut builder = PathBuilder::new();
+        if mass > 0.0 && sub_dt > 0.0 {
+            let start = weighted / mass;
+            let end = start + p / mass / sub_dt * MOMENTUM_ARROW_SECONDS;
+            let back = (start - end).normalize_or_zero() * 0.5;
+            builder.move_to(start * GAME_SCALE);
+            builder.line_to(end * GAME_SCALE);
+            for side in [back.perp(), -back.perp()] {
+                builder.move_to(end * GAME_SCALE);
+                builder.line_to((end + back + side * 0.5) * GAME_SCALE);
+            }
+        }
+        *path = builder.build();
+    }
+
+    // |p| over time along the top wall, scaled to the largest value in the history. It stays
+    // inside the bounds, the camera only keeps those in view.
+    if let Ok((mut path, _)) = plot.get_single_mut() {
+        let rect = bounds.to_rect();
+        let largest = momentum
+            .history
+            .iter()
+            .map(|p| p.length())
+            .fold(f32::EPSILON, f32::max);
+        let area = Rect::new(
+            rect.min.x,
+            rect.max.y - 1.0 - MOMENTUM_PLOT_HEIGHT,
+            rect.max.x,
+            rect.max.y - 1.0,
+        );
+        *path = line_plot(
+            momentum.history.iter().map(|p| p.length()),
+            MOMENTUM_HISTORY,
+            area,
+            0.0..largest,
+        );
+    }
+}
+
+/// Polyline through `values` from left to right across `area`, room for `capacity` of them, with
+/// `range` spanning its height
+fn line_plot(
+    values: impl Iterator<Item = f32>,
+    capacity: usize,
+    area: Rect,
+    range: std::ops::Range<f32>,
+) -> Path {
+    let step = area.width() / (capacity.max(2) - 1) as f32;
+    let span = (range.end - range.start).max(f32::EPSILON);
+    let mut builder = PathBuilder::new();
+    for (i, value) in values.enumerate() {
+        let t = ((value - range.start) / span).clamp(0.0, 1.0);
+        let position = Vec2::new(area.min.x + i as f32 * step, area.min.y + t * area.height());
+        if i == 0 {
+            builder.move_to(position * GAME_SCALE);
+        } else {
+            builder.line_to(position * GAME_SCALE);
+        }
+    }
+    builder.build()
+}
+
+fn update_visual_point(mut query: Query<(&Point, &mut Transform)>) {
+    for (point, mut transform) in query.iter_mut() {
+        transform.translation = Vec3::new(point.x * GAME_SCALE, point.y * GAME_SCALE, 0.0);
+    }
+}
+
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+struct SpawnTimer {
+    timer: Timer,
+    id: i32,
+    /// Each interval is drawn from `base * (1 - jitter_fraction)..base * (1 + jitter_fraction)`
+    jitter_fraction: f32,
+    /// Points spawned every time the timer fires
+    burst_size: u8,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Reflect)]
+#[serde(rename_all = "snake_case")]
+enum SpawnPattern {
+    /// Every point spawns from the middle of the bounds
+    Fixed,
+    /// Spawn positions sweep from the left wall to the right wall and wrap around
+    Sweep,
+    /// Spawn at a random position between the walls
+    Scatter,
+}
+
+/// Draws spawn positions, radii or spawn times from a distribution instead of the ranges
+#[derive(Deserialize, Clone, Copy, Debug, Default, Reflect)]
+#[serde(rename_all = "snake_case")]
+enum SpawnDistribution {
+    #[default]
+    Uniform,
+    /// Spawn positions scatter around `mean` instead of following the pattern
+    Gaussian { mean: Vec2, std: f32 },
+    /// Spawns form a Poisson process with `lambda` points per second on average, which comes out
+    /// much burstier than the fixed timer
+    Poisson { lambda: f32 },
+    /// Radii within `radius_range` with `p(r) ∝ r^-exponent`, mostly small with the odd big one
+    PowerLaw { exponent: f32 },
+}
+
+/// Standard normal sample via the Box-Muller transform
+fn sample_gaussian(rng: &mut impl Rng) -> f32 {
+    let u1 = rng.gen::<f32>().max(f32::MIN_POSITIVE);
+    let u2 = rng.gen::<f32>();
+    (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
+
+/// Inverse transform sample of `p(r) ∝ r^-exponent` on `range`
+fn sample_power_law(range: (f32, f32), exponent: f32, rng: &mut impl Rng) -> f32 {
+    let (min, max) = range;
+    if min <= 0.0 || min >= max {
+        return min;
+    }
+    let u = rng.gen::<f32>();
+    let k = 1.0 - exponent;
+    if k.abs() < f32::EPSILON {
+        min * (max / min).powf(u)
+    } else {
+        ((max.powf(k) - min.powf(k)) * u + min.powf(k)).powf(1.0 / k)
+    }
+}
+
+/// The part of [`PointSpawner`] that can be changed through the config file
+#[derive(Deserialize)]
+struct SpawnConfig {
+    rate: f32,
+    radius_range: (f32, f32),
+    velocity_range: ([f32; 2], [f32; 2]),
+    pattern: SpawnPattern,
+    #[serde(default = "default_categories")]
+    categories: u32,
+    #[serde(default)]
+    distribution: SpawnDistribution,
+}
+
+fn default_categories() -> u32 {
+    1
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct PointSpawner {
+    config_path: Option<PathBuf>,
+    /// Points spawned per second
+    rate: f32,
+    radius_range: (f32, f32),
+    /// Smallest and largest initial (x, y) velocity
+    velocity_range: ([f32; 2], [f32; 2]),
+    pattern: SpawnPattern,
+    /// Spawned points cycle through categories `0..categories`
+    categories: u32,
+    distribution: SpawnDistribution,
+}
+
+impl Default for PointSpawner {
+    fn default() -> Self {
+        PointSpawner {
+            config_path: None,
+            rate: 2.0,
+            radius_range: (1.0, 1.0),
+            velocity_range: ([0.1, 0.02], [0.1, 0.02]),
+            pattern: SpawnPattern::Fixed,
+            categories: 1,
+            distribution: SpawnDistribution::Uniform,
+        }
+    }
+}
+
+impl PointSpawner {
+    /// Re-reads the config file, leaving the spawner untouched if it can't be parsed
+    fn reload(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(path) = &self.config_path else {
+            return Ok(());
+        };
+
+        let config: SpawnConfig = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if config.rate <= 0.0 {
+            return Err("rate must be positive".into());
+        }
+
+        self.rate = config.rate;
+        self.radius_range = config.radius_range;
+        self.velocity_range = config.velocity_range;
+        self.pattern = config.pattern;
+        self.categories = config.categories.max(1);
+        self.distribution = config.distribution;
+        Ok(())
+    }
+
+    fn sample_radius(&self, rng: &mut impl Rng) -> f32 {
+        match self.distribution {
+            SpawnDistribution::PowerLaw { exponent } => {
+                sample_power_law(self.radius_range, exponent, rng)
+            }
+            _ => sample_range(self.radius_range, rng),
+        }
+    }
+
+    /// Where the `index`th point of a burst starts
+    fn spawn_position(&self, id: i32, index: u8, bounds: &Bounds, rng: &mut impl Rng) -> Vec2 {
+        if let SpawnDistribution::Gaussian { mean, std } = self.distribution {
+            let offset = Vec2::new(sample_gaussian(rng), sample_gaussian(rng)) * std;
+            let rect = bounds.to_rect();
+            return (mean + offset).clamp(rect.min, rect.max);
+        }
+        // Stack a burst upwards so no two points start on top of each other
+        let y = SPAWN_Y + index as f32 * self.radius_range.1 * 2.0;
+        Vec2::new(self.spawn_x(id, bounds, rng), y)
+    }
+
+    /// Time until the next spawn, `jitter` spreads it around the interval set by `rate`
+    fn next_interval(&self, jitter: f32, rng: &mut impl Rng) -> f32 {
+        if let SpawnDistribution::Poisson { lambda } = self.distribution {
+            // Waiting times of a Poisson process are exponentially distributed
+            let u = rng.gen::<f32>().max(f32::MIN_POSITIVE);
+            return -u.ln() / lambda.max(f32::EPSILON);
+        }
+        let base = self.interval().as_secs_f32();
+        let jitter = jitter.clamp(0.0, 1.0);
+        sample_range((base * (1.0 - jitter), base * (1.0 + jitter)), rng)
+    }
+
+    /// The reflect editor can push `rate` to 0 or below, which would be an infinite interval
+    fn interval(&self) -> Duration {
+        Duration::from_secs_f32(1.0 / self.rate.max(f32::EPSILON))
+    }
+
+    fn spawn_x(&self, id: i32, bounds: &Bounds, rng: &mut impl Rng) -> f32 {
+        let rect = bounds.to_rect();
+        match self.pattern {
+            SpawnPattern::Fixed => rect.center().x,
+            SpawnPattern::Sweep => {
+                rect.min.x + (id as f32 * self.radius_range.1 * 2.0) % rect.width()
+            }
+            SpawnPattern::Scatter => sample_range((rect.min.x, rect.max.x), rng),
+        }
+    }
+}
+
+fn sample_range(range: (f32, f32), rng: &mut impl Rng) -> f32 {
+    if range.0 < range.1 {
+        rng.gen_range(range.0..range.1)
+    } else {
+        range.0
+    }
+}
+
+/// Keeps the file watcher alive and hands its events to [`reload_spawn_config_system`]
+struct SpawnConfigWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+}
+
+impl SpawnConfigWatcher {
+    fn new(spawner: &PointSpawner) -> Option<Self> {
+        let path = spawner.config_path.as_ref()?;
+        // Editors tend to save by replacing the file, so watch the directory it lives in
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty())?;
+
+        let (sender, events) = channel();
+        let watcher = notify::recommended_watcher(sender).and_then(|mut watcher| {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            Ok(watcher)
+        });
+
+        match watcher {
+            Ok(watcher) => Some(SpawnConfigWatcher {
+                _watcher: watcher,
+                events,
+            }),
+            Err(e) => {
+                warn!("Could not watch {:?} for spawn config changes: {}", dir, e);
+                None
+            }
+        }
+    }
+}
+
+fn reload_spawner(spawner: &mut PointSpawner, spawn_timer: &mut SpawnTimer) {
+    match spawner.reload() {
+        Ok(()) => {
+            spawn_timer.timer.set_duration(spawner.interval());
+            info!("Loaded spawn config from {:?}", spawner.config_path);
+        }
+        Err(e) => warn!(
+            "Could not load spawn config from {:?}: {}",
+            spawner.config_path, e
+        ),
+    }
+}
+
+fn load_spawn_config(mut spawner: ResMut<PointSpawner>, mut spawn_timer: ResMut<SpawnTimer>) {
+    reload_spawner(&mut spawner, &mut spawn_timer);
+}
+
+fn watch_spawn_config(world: &mut World) {
+    if let Some(watcher) = SpawnConfigWatcher::new(world.resource::<PointSpawner>()) {
+        world.insert_non_send_resource(watcher);
+    }
+}
+
+fn reload_spawn_config_system(
+    watcher: Option<NonSend<SpawnConfigWatcher>>,
+    mut spawner: ResMut<PointSpawner>,
+    mut spawn_timer: ResMut<SpawnTimer>,
+) {
+    let Some(watcher) = watcher else {
+        return;
+    };
+    let Some(path) = &spawner.config_path else {
+        return;
+    };
+
+    // Drain everything so a burst of events from one save only triggers one reload
+    let changed = watcher
+        .events
+        .try_iter()
+        .filter(|event| match event {
+            Ok(event) => !event.kind.is_access() && event.paths.iter().any(|p| p.ends_with(path)),
+            Err(_) => false,
+        })
+        .count()
+        > 0;
+    if changed {
+        reload_spawner(&mut spawner, &mut spawn_timer);
+    }
+}
+
+fn spawn_item(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut config: ResMut<SpawnTimer>,
+    spawner: Res<PointSpawner>,
+    bounds: Res<Bounds>,
+    phase: Res<PhaseController>,
+    tilt: Res<TiltExperiment>,
+) {
+    // New points landing on the pile would look like an avalanche
+    if !phase.spawning() || tilt.active {
+        return;
+    }
+    config.timer.tick(time.delta());
+
+    if config.timer.finished() {
+        let mut rng = rand::thread_rng();
+        let (min_vel, max_vel) = spawner.velocity_range;
+        for i in 0..config.burst_size {
+            let radius = spawner.sample_radius(&mut rng);
+            let position = spawner.spawn_position(config.id, i, &bounds, &mut rng);
+            let vel_x = sample_range((min_vel[0], max_vel[0]), &mut rng);
+            let vel_y = sample_range((min_vel[1], max_vel[1]), &mut rng);
+
+            let point = Point::new(config.id, position.x, position.y, vel_x, vel_y)
+                .with_radius(radius)
+                .with_category(config.id as u32 % spawner.categories.max(1));
+            spawn_point(&mut commands, point, time.elapsed_seconds());
+            config.id += 1;
+        }
+
+        let interval = spawner.next_interval(config.jitter_fraction, &mut rng);
+        config
+            .timer
+            .set_duration(Duration::from_secs_f32(interval.max(f32::EPSILON)));
+    }
+}
+
+/// Resources the reflect editor can cycle through, and which of their fields is selected
+#[derive(Resource, Default)]
+struct ReflectEditor {
+    resources: Vec<TypeId>,
+    resource: usize,
+    field: usize,
+}
+
+impl ReflectEditor {
+    fn with<T: Resource>(mut self) -> Self {
+        self.resources.push(TypeId::of::<T>());
+        self
+    }
+}
+
+/// Nudges a numeric (or boolean) field up or down, returns false for anything it can't edit
+fn nudge_field(field: &mut dyn Reflect, direction: i32) -> bool {
+    if let Some(value) = field.downcast_mut::<f32>() {
+        // Relative steps so both gravity and small tolerances are usable
+        *value += direction as f32 * (value.abs() * 0.1).max(0.01);
+    } else if let Some(value) = field.downcast_mut::<i32>() {
+        *value += direction;
+    } else if let Some(value) = field.downcast_mut::<u8>() {
+        *value = (*value as i32 + direction).clamp(0, u8::MAX as i32) as u8;
+    } else if let Some(value) = field.downcast_mut::<u32>() {
+        *value = value.saturating_add_signed(direction);
+    } else if let Some(value) = field.downcast_mut::<u64>() {
+        *value = value.saturating_add_signed(direction as i64);
+    } else if let Some(value) = field.downcast_mut::<bool>() {
+        *value = !*value;
+    } else {
+        return false;
+    }
+    true
+}
+
+/// Minimal inspector: left/right picks a resource, up/down picks a field and +/- changes it
+fn reflect_editor_system(world: &mut World) {
+    let keys = world.resource::<Input<KeyCode>>();
+    let pressed = |codes: &[KeyCode]| keys.any_just_pressed(codes.iter().copied()) as i32;
+    let resource_step = pressed(&[KeyCode::Right]) - pressed(&[KeyCode::Left]);
+    let field_step = pressed(&[KeyCode::Down]) - pressed(&[KeyCode::Up]);
+    let change = pressed(&[KeyCode::Equals, KeyCode::NumpadAdd])
+        - pressed(&[KeyCode::Minus, KeyCode::NumpadSubtract]);
+    if resource_step == 0 && field_step == 0 && change == 0 {
+        return;
+    }
+
+    world.resource_scope(|world, mut editor: Mut<ReflectEditor>| {
+        if editor.resources.is_empty() {
+            return;
+        }
+        if resource_step != 0 {
+            let count = editor.resources.len() as i32;
+            editor.resource = (editor.resource as i32 + resource_step).rem_euclid(count) as usize;
+            editor.field = 0;
+        }
+
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        let Some(registration) = registry.get(editor.resources[editor.resource]) else {
+            return;
+        };
+        let Some(mut resource) = registration
+            .data::<ReflectResource>()
+            .and_then(|reflect_resource| reflect_resource.reflect_mut(world))
+        else {
+            return;
+        };
+        let ReflectMut::Struct(fields) = resource.reflect_mut() else {
+            return;
+        };
+        if fields.field_len() == 0 {
+            return;
+        }
+
+        let count = fields.field_len() as i32;
+        editor.field = (editor.field as i32 + field_step).rem_euclid(count) as usize;
+        let name = fields.name_at(editor.field).unwrap_or_default().to_string();
+        let Some(field) = fields.field_at_mut(editor.field) else {
+            return;
+        };
+
+        if change != 0 && !nudge_field(field, change) {
+            info!("{}.{} can't be edited", registration.short_name(), name);
+        } else {
+            info!("{}.{} = {:?}", registration.short_name(), name, field);
+        }
+    });
+}
+
+/// Cursor position in simulation units, the same space [`Point`] lives in
+fn cursor_world_position(
+    windows: &Windows,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Vec2> {
+    let cursor = windows.get_primary()?.cursor_position()?;
+    let ray = camera.viewport_to_world(camera_transform, cursor)?;
+    Some(ray.origin.truncate() / GAME_SCALE)
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct BurstSpawn {
+    count: usize,
+    spread: f32,
+    key: KeyCode,
+}
+
+impl Default for BurstSpawn {
+    fn default() -> Self {
+        BurstSpawn {
+            count: 30,
+            spread: 4.0,
+            key: KeyCode::B,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn burst_spawn_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    burst: Res<BurstSpawn>,
+    spawner: Res<PointSpawner>,
+    mut spawn_timer: ResMut<SpawnTimer>,
+    windows: Res<Windows>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+) {
+    if !keys.just_pressed(burst.key) {
+        return;
+    }
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+    let Some(center) = cursor_world_position(&windows, camera, camera_transform) else {
+        return;
+    };
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..burst.count {
+        // sqrt keeps the points evenly spread over the disc instead of bunched in the middle
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        let distance = burst.spread * rng.gen::<f32>().sqrt();
+        let direction = Vec2::new(angle.cos(), angle.sin());
+        let position = center + direction * distance;
+        let velocity = direction * BURST_SPEED;
+        let radius = sample_range(spawner.radius_range, &mut rng);
+
+        let id = spawn_timer.id;
+        let point =
+            Point::new(id, position.x, position.y, velocity.x, velocity.y).with_radius(radius);
+        spawn_point(&mut commands, point, time.elapsed_seconds());
+        spawn_timer.id += 1;
+    }
+}
+
+/// Fills the bottom half of the bounds with a random mix of small and large points
+#[allow(clippy::too_many_arguments)]
+fn spawn_mixed_sizes(
+    commands: &mut Commands,
+    bounds: &Bounds,
+    spawn_timer: &mut SpawnTimer,
+    time: f32,
+    small_count: usize,
+    large_count: usize,
+    small_r: f32,
+    large_r: f32,
+) {
+    let mut rng = rand::thread_rng();
+    let rect = bounds.to_rect();
+    let mut radii = vec![small_r; small_count];
+    radii.extend(vec![large_r; large_count]);
+    radii.shuffle(&mut rng);
+
+    for radius in radii {
+        let x = sample_range((rect.min.x + radius, rect.max.x - radius), &mut rng);
+        let y = sample_range((rect.min.y + radius, rect.center().y), &mut rng);
+        let point = Point::new(spawn_timer.id, x, y, 0.0, 0.0).with_radius(radius);
+        spawn_point(commands, point, time);
+        spawn_timer.id += 1;
+    }
+}
+
+/// Brazil-nut effect: shake a mix of sizes and watch the large points rise
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct BrazilNutDemo {
+    active: bool,
+    small_count: usize,
+    large_count: usize,
+    small_radius: f32,
+    large_radius: f32,
+    /// Gravity swings between `1 - amplitude` and `1 + amplitude` times its normal value
+    shake_amplitude: f32,
+    shake_frequency: f32,
+    /// Gravity before shaking started
+    rest_gravity: f32,
+    report_timer: Timer,
+    /// Segregation index of the last [`SEGREGATION_HISTORY`] reports
+    history: Vec<f32>,
+}
+
+impl Default for BrazilNutDemo {
+    fn default() -> Self {
+        BrazilNutDemo {
+            active: false,
+            small_count: 300,
+            large_count: 12,
+            small_radius: 0.5,
+            large_radius: 1.5,
+            shake_amplitude: 1.5,
+            shake_frequency: 4.0,
+            rest_gravity: GRAVITY,
+            report_timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+            history: Vec::new(),
+        }
+    }
+}
+
+/// Plot of [`BrazilNutDemo::history`] inside the top of the bounds, shown while the demo runs
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct SegregationPlot;
+
+fn spawn_segregation_plot(mut commands: Commands) {
+    let mut bundle = GeometryBuilder::build_as(
+        &PathBuilder::new().build(),
+        DrawMode::Stroke(StrokeMode::new(Color::ORANGE, 1.0)),
+        Transform::from_xyz(0.0, 0.0, 2.0),
+    );
+    bundle.visibility.is_visible = false;
+    commands.spawn((bundle, SegregationPlot));
+}
+
+/// Mean height of the large points above the mean of all points, as a fraction of the bounds
+/// height. Positive once the large ones have risen to the top.
+fn segregation_index(points: &Query<&Point>, large_radius: f32, bounds: &Bounds) -> f32 {
+    let mean_y = |points: &mut dyn Iterator<Item = &Point>| {
+        let (sum, count) = points.fold((0.0, 0), |(sum, count), point| (sum + point.y, count + 1));
+        sum / (count as f32).max(1.0)
+    };
+    let large = mean_y(&mut points.iter().filter(|point| point.radius >= large_radius));
+    let all = mean_y(&mut points.iter());
+    (large - all) / bounds.to_rect().height()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn brazil_nut_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    mut demo: ResMut<BrazilNutDemo>,
+    mut gravity: ResMut<GravityConfig>,
+    mut spawn_timer: ResMut<SpawnTimer>,
+    bounds: Res<Bounds>,
+    points: Query<&Point>,
+    mut plot: Query<(&mut Path, &mut Visibility), With<SegregationPlot>>,
+) {
+    if keys.just_pressed(bindings.brazil_nut) {
+        demo.active = !demo.active;
+        for (_, mut visibility) in plot.iter_mut() {
+            visibility.is_visible = demo.active;
+        }
+        if demo.active {
+            demo.rest_gravity = gravity.y;
+            demo.report_timer.reset();
+            demo.history.clear();
+            spawn_mixed_sizes(
+                &mut commands,
+                &bounds,
+                &mut spawn_timer,
+                time.elapsed_seconds(),
+                demo.small_count,
+                demo.large_count,
+                demo.small_radius,
+                demo.large_radius,
+            );
+        } else {
+            gravity.y = demo.rest_gravity;
+        }
+    }
+    if !demo.active {
+        return;
+    }
+
+    let phase = std::f32::consts::TAU * demo.shake_frequency * time.elapsed_seconds();
+    gravity.y = demo.rest_gravity * (1.0 + demo.shake_amplitude * phase.sin());
+
+    if demo.report_timer.tick(time.delta()).just_finished() {
+        let index = segregation_index(&points, demo.large_radius, &bounds);
+        info!(
+            "Segregation index at {:.1}s: {:.3}",
+            time.elapsed_seconds(),
+            index
+        );
+        demo.history.push(index);
+        if demo.history.len() > SEGREGATION_HISTORY {
+            demo.history.remove(0);
+        }
+
+        // -0.5 at the bottom of the plot, fully mixed in the middle, 0.5 at the top
+        let rect = bounds.to_rect();
+        // Below the momentum plot, so both can be shown at once
+        let top = rect.max.y - 2.0 - MOMENTUM_PLOT_HEIGHT;
+        let area = Rect::new(rect.min.x, top - SEGREGATION_PLOT_HEIGHT, rect.max.x, top);
+        for (mut path, _) in plot.iter_mut() {
+            *path = line_plot(
+                demo.history.iter().copied(),
+                SEGREGATION_HISTORY,
+                area,
+                -0.5..0.5,
+            );
+        }
+    }
+}
+
+/// Slowly tilts gravity until the pile avalanches, `TILT_RUNS` times, to find the friction angle.
+/// Spawning pauses while it runs. Each run starts from the pile the last avalanche left behind,
+/// settled again under straight-down gravity, so later runs measure a pile that has already slid
+/// rather than a fresh one.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct TiltExperiment {
+    active: bool,
+    /// Angle of gravity away from straight down, in radians
+    current_angle: f32,
+    /// Radians per second
+    angular_speed: f32,
+    /// Gravity magnitude, taken when the experiment starts
+    strength: f32,
+    /// Let the pile come to rest before each run
+    settle_timer: Timer,
+    sample_timer: Timer,
+    last_spread: Option<f32>,
+    /// Points that were resting when the run started, the only ones whose heights are sampled
+    #[reflect(ignore)]
+    pile: HashSet<Entity>,
+    avalanche_angles: Vec<f32>,
+}
+
+impl Default for TiltExperiment {
+    fn default() -> Self {
+        TiltExperiment {
+            active: false,
+            current_angle: 0.0,
+            angular_speed: 0.02,
+            strength: -GRAVITY,
+            settle_timer: Timer::from_seconds(3.0, TimerMode::Once),
+            sample_timer: Timer::from_seconds(0.25, TimerMode::Repeating),
+            last_spread: None,
+            pile: HashSet::new(),
+            avalanche_angles: Vec::new(),
+        }
+    }
+}
+
+impl TiltExperiment {
+    fn start_run(&mut self) {
+        self.current_angle = 0.0;
+        self.last_spread = None;
+        self.pile.clear();
+        self.settle_timer.reset();
+    }
+
+    fn gravity(&self) -> Vec2 {
+        Vec2::new(self.current_angle.sin(), -self.current_angle.cos()) * self.strength
+    }
+}
+
+fn tilt_system(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    mut experiment: ResMut<TiltExperiment>,
+    mut gravity: ResMut<GravityConfig>,
+) {
+    if keys.just_pressed(bindings.tilt) {
+        experiment.active = !experiment.active;
+        if experiment.active {
+            experiment.strength = Vec2::new(gravity.x, gravity.y).length();
+            experiment.avalanche_angles.clear();
+            experiment.start_run();
+        } else {
+            gravity.x = 0.0;
+            gravity.y = -experiment.strength;
+        }
+    }
+    if !experiment.active {
+        return;
+    }
+
+    if experiment.settle_timer.tick(time.delta()).finished() {
+        experiment.current_angle += experiment.angular_speed * time.delta_seconds();
+    }
+    let tilted = experiment.gravity();
+    gravity.x = tilted.x;
+    gravity.y = tilted.y;
+}
+
+fn avalanche_detector_system(
+    time: Res<Time>,
+    mut experiment: ResMut<TiltExperiment>,
+    mut gravity: ResMut<GravityConfig>,
+    bounds: Res<Bounds>,
+    points: Query<(Entity, &Point)>,
+) {
+    if !experiment.active || !experiment.settle_timer.finished() {
+        return;
+    }
+    if !experiment.sample_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    if experiment.pile.is_empty() {
+        let sub_dt = time.delta_seconds() / SUBSTEPS as f32;
+        let floor = bounds.min_y as f32;
+        experiment.pile = points
+            .iter()
+            .filter(|(_, point)| {
+                let speed = Vec2::new(point.vel_x(), point.vel_y()).length() / sub_dt;
+                let supported =
+                    point.coordination_number > 0 || point.y - point.radius <= floor + 0.1;
+                speed < SETTLED_SPEED && supported
+            })
+            .map(|(entity, _)| entity)
+            .collect();
+        return;
+    }
+
+    let heights: Vec<f32> = points
+        .iter()
+        .filter(|(entity, _)| experiment.pile.contains(entity))
+        .map(|(_, point)| point.y)
+        .collect();
+    let count = heights.len().max(1) as f32;
+    let mean = heights.iter().sum::<f32>() / count;
+    let variance = heights.iter().map(|y| (y - mean).powi(2)).sum::<f32>() / count;
+    let spread = variance.sqrt();
+
+    let avalanche = matches!(experiment.last_spread,
+        Some(last) if spread > last * (1.0 + AVALANCHE_THRESHOLD));
+    experiment.last_spread = Some(spread);
+    if !avalanche {
+        return;
+    }
+
+    let angle = experiment.current_angle;
+    experiment.avalanche_angles.push(angle);
+    info!(
+        "Avalanche {} at {:.1} degrees",
+        experiment.avalanche_angles.len(),
+        angle.to_degrees()
+    );
+
+    if experiment.avalanche_angles.len() < TILT_RUNS {
+        experiment.start_run();
+        return;
+    }
+
+    let angles = &experiment.avalanche_angles;
+    let mean = angles.iter().sum::<f32>() / angles.len() as f32;
+    let std = (angles.iter().map(|a| (a - mean).powi(2)).sum::<f32>() / angles.len() as f32).sqrt();
+    info!(
+        "Friction angle over {} runs: {:.1} +- {:.1} degrees",
+        angles.len(),
+        mean.to_degrees(),
+        std.to_degrees()
+    );
+    experiment.active = false;
+    gravity.x = 0.0;
+    gravity.y = -experiment.strength;
+}
+
+/// Spawns `count` points side by side along `y`. Point `i` starts with a horizontal velocity of
+/// `amplitude * sin(2π * i / wavelength)`, so neighbours squeeze together and pull apart.
+#[allow(clippy::too_many_arguments)]
+fn spawn_wave(
+    commands: &mut Commands,
+    bounds: &Bounds,
+    spawn_timer: &mut SpawnTimer,
+    time: f32,
+    y: f32,
+    count: usize,
+    wavelength: f32,
+    amplitude: f32,
+) -> Vec<Entity> {
+    let rect = bounds.to_rect();
+    let spacing = rect.width() / count.max(1) as f32;
+    let radius = (spacing / 2.0 * 0.9).min(1.0);
+
+    (0..count)
+        .map(|i| {
+            let x = rect.min.x + spacing * (i as f32 + 0.5);
+            let phase = std::f32::consts::TAU * i as f32 / wavelength;
+            let point =
+                Point::new(spawn_timer.id, x, y, amplitude * phase.sin(), 0.0).with_radius(radius);
+            spawn_timer.id += 1;
+            spawn_point(commands, point, time)
+        })
+        .collect()
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct WaveDemo {
+    count: usize,
+    wavelength: f32,
+    amplitude: f32,
+    /// Expected wave speed, compared against the measured one
+    phase_speed: f32,
+    points: Vec<Entity>,
+    /// (time, x) of the tightest gap between neighbours
+    peak_samples: Vec<(f32, f32)>,
+    sample_timer: Timer,
+}
+
+impl Default for WaveDemo {
+    fn default() -> Self {
+        WaveDemo {
+            count: 30,
+            wavelength: 10.0,
+            amplitude: 0.05,
+            phase_speed: 20.0,
+            points: Vec::new(),
+            peak_samples: Vec::new(),
+            sample_timer: Timer::from_seconds(0.1, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Least squares slope of the second value over the first
+fn fit_slope(samples: &[(f32, f32)]) -> f32 {
+    let n = samples.len() as f32;
+    let mean_t = samples.iter().map(|(t, _)| t).sum::<f32>() / n;
+    let mean_x = samples.iter().map(|(_, x)| x).sum::<f32>() / n;
+    let covariance: f32 = samples
+        .iter()
+        .map(|(t, x)| (t - mean_t) * (x - mean_x))
+        .sum();
+    let variance: f32 = samples.iter().map(|(t, _)| (t - mean_t).powi(2)).sum();
+    covariance / variance.max(f32::EPSILON)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn wave_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    mut demo: ResMut<WaveDemo>,
+    mut spawn_timer: ResMut<SpawnTimer>,
+    bounds: Res<Bounds>,
+    points: Query<&Point>,
+) {
+    if keys.just_pressed(bindings.wave) {
+        // Along the floor, so gravity doesn't break the row up
+        let y = bounds.min_y as f32 + 1.0;
+        demo.points = spawn_wave(
+            &mut commands,
+            &bounds,
+            &mut spawn_timer,
+            time.elapsed_seconds(),
+            y,
+            demo.count,
+            demo.wavelength,
+            demo.amplitude,
+        );
+        demo.peak_samples.clear();
+        demo.sample_timer.reset();
+        return;
+    }
+    if demo.points.is_empty() || !demo.sample_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let mut xs: Vec<f32> = points
+        .iter_many(&demo.points)
+        .map(|point| point.x)
+        .collect();
+    xs.sort_by(f32::total_cmp);
+    let Some(peak) = xs
+        .windows(2)
+        .min_by(|a, b| (a[1] - a[0]).total_cmp(&(b[1] - b[0])))
+        .map(|pair| (pair[0] + pair[1]) / 2.0)
+    else {
+        return;
+    };
+    demo.peak_samples.push((time.elapsed_seconds(), peak));
+
+    let elapsed = time.elapsed_seconds() - demo.peak_samples[0].0;
+    if elapsed >= WAVE_MEASURE_SECONDS {
+        info!(
+            "Wave speed {:.2} units/s, expected {:.2}",
+            fit_slope(&demo.peak_samples).abs(),
+            demo.phase_speed
+        );
+        demo.points.clear();
+    }
+}
+
+/// A convex shape the points bounce off. It moves with its own velocity and is never pushed back.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct PolygonObstacle {
+    /// In simulation units, counter-clockwise
+    vertices: Vec<Vec2>,
+    /// Simulation units per second
+    velocity: Vec2,
+}
+
+impl PolygonObstacle {
+    fn regular(center: Vec2, radius: f32, sides: usize, velocity: Vec2) -> Self {
+        let vertices = (0..sides)
+            .map(|i| {
+                let angle = std::f32::consts::TAU * i as f32 / sides as f32;
+                center + radius * Vec2::new(angle.cos(), angle.sin())
+            })
+            .collect();
+        PolygonObstacle { vertices, velocity }
+    }
+
+    fn centroid(&self) -> Vec2 {
+        self.vertices.iter().sum::<Vec2>() / self.vertices.len() as f32
+    }
+
+    fn project(&self, axis: Vec2) -> (f32, f32) {
+        self.vertices
+            .iter()
+            .map(|vertex| vertex.dot(axis))
+            .fold((f32::MAX, f32::MIN), |(min, max), d| {
+                (min.min(d), max.max(d))
+            })
+    }
+
+    /// Separating axis test against a circle. Returns the shortest push that moves the circle out
+    /// of the polygon, or `None` if one of the axes separates them.
+    fn circle_penetration(&self, center: Vec2, radius: f32) -> Option<Vec2> {
+        let closest = self.vertices.iter().copied().min_by(|a, b| {
+            a.distance_squared(center)
+                .total_cmp(&b.distance_squared(center))
+        })?;
+        let edge_normals = self
+            .vertices
+            .iter()
+            .zip(self.vertices.iter().cycle().skip(1))
+            .map(|(a, b)| (*b - *a).perp().normalize_or_zero());
+
+        let mut best: Option<(f32, Vec2)> = None;
+        for axis in edge_normals.chain(std::iter::once((center - closest).normalize_or_zero())) {
+            if axis == Vec2::ZERO {
+                continue;
+            }
+            let (min, max) = self.project(axis);
+            let projected = center.dot(axis);
+            let overlap = (max - (projected - radius)).min(projected + radius - min);
+            if overlap <= 0.0 {
+                return None;
+            }
+            if best.is_none_or(|(smallest, _)| overlap < smallest) {
+                best = Some((overlap, axis));
+            }
+        }
+
+        let (overlap, axis) = best?;
+        let outward = if (center - self.centroid()).dot(axis) < 0.0 {
+            -axis
+        } else {
+            axis
+        };
+        Some(outward * overlap)
+    }
+}
+
+fn spawn_obstacle(commands: &mut Commands, obstacle: PolygonObstacle) {
+    let centroid = obstacle.centroid();
+    let shape = shapes::Polygon {
+        points: obstacle
+            .vertices
+            .iter()
+            .map(|vertex| (*vertex - centroid) * GAME_SCALE)
+            .collect(),
+        closed: true,
+    };
+    commands.spawn((
+        GeometryBuilder::build_as(
+            &shape,
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(Color::DARK_GRAY),
+                outline_mode: StrokeMode::new(Color::WHITE, 2.0),
+            },
+            Transform::from_translation((centroid * GAME_SCALE).extend(1.0)),
+        ),
+        obstacle,
+    ));
+}
+
+fn polygon_obstacle_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    bounds: Res<Bounds>,
+    mut obstacles: Query<(&mut PolygonObstacle, &mut Transform)>,
+) {
+    if keys.just_pressed(bindings.obstacle) {
+        let center = bounds.to_rect().center();
+        spawn_obstacle(
+            &mut commands,
+            PolygonObstacle::regular(center, 5.0, 5, Vec2::new(8.0, 3.0)),
+        );
+    }
+
+    let rect = bounds.to_rect();
+    for (mut obstacle, mut transform) in obstacles.iter_mut() {
+        let step = obstacle.velocity * time.delta_seconds();
+        for vertex in obstacle.vertices.iter_mut() {
+            *vertex += step;
+        }
+
+        let (min_x, max_x) = obstacle.project(Vec2::X);
+        let (min_y, max_y) = obstacle.project(Vec2::Y);
+        if (min_x < rect.min.x && obstacle.velocity.x < 0.0)
+            || (max_x > rect.max.x && obstacle.velocity.x > 0.0)
+        {
+            obstacle.velocity.x = -obstacle.velocity.x;
+        }
+        if (min_y < rect.min.y && obstacle.velocity.y < 0.0)
+            || (max_y > rect.max.y && obstacle.velocity.y > 0.0)
+        {
+            obstacle.velocity.y = -obstacle.velocity.y;
+        }
+
+        let centroid = obstacle.centroid() * GAME_SCALE;
+        transform.translation.x = centroid.x;
+        transform.translation.y = centroid.y;
+    }
+}
+
+/// Obstacles push like walls, so their impulse goes into the same part of the [`MomentumTracker`]
+fn particle_vs_polygon_system(
+    time: Res<Time>,
+    obstacles: Query<&PolygonObstacle>,
+    damping: Res<DampingWall>,
+    mut points: Query<&mut Point>,
+    mut momentum: ResMut<MomentumTracker>,
+) {
+    let sub_dt = time.delta_seconds() / SUBSTEPS as f32;
+    for obstacle in obstacles.iter() {
+        for mut point in points.iter_mut() {
+            let center = Vec2::new(point.x, point.y);
+            let Some(push) = obstacle.circle_penetration(center, point.radius) else {
+                continue;
+            };
+            let before = point.momentum();
+            let normal = push.normalize_or_zero();
+            point.x += push.x;
+            point.y += push.y;
+
+            // Bounce off the obstacle as if it were a wall moving along with its velocity
+            let vel = Vec2::new(point.vel_x(), point.vel_y());
+            let wall_vel = obstacle.velocity.dot(normal) * sub_dt;
+            let normal_vel = vel.dot(normal);
+            if normal_vel < wall_vel {
+                let vel = vel + normal * (wall_vel - normal_vel) * (1.0 + damping.bounce);
+                point.old_x = point.x - vel.x;
+                point.old_y = point.y - vel.y;
+            }
+            let impulse = point.momentum() - before;
+            momentum.wall_impulse_x += impulse.x;
+            momentum.wall_impulse_y += impulse.y;
+        }
+    }
+}
+
+/// Poiseuille flow: a pressure drop pushes the points through the channel between `inlet_x` and
+/// `outlet_x`, and points that leave through the outlet come back in through the inlet. Both are
+/// placed [`CHANNEL_MARGIN`] inside the side walls when the drive is switched on.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct PressureDrive {
+    active: bool,
+    inlet_x: f32,
+    outlet_x: f32,
+    delta_p: f32,
+    fluid_density: f32,
+    /// Only used for the analytical profile, the points have no viscosity of their own
+    viscosity: f32,
+    report_timer: Timer,
+}
+
+impl Default for PressureDrive {
+    fn default() -> Self {
+        PressureDrive {
+            active: false,
+            inlet_x: 0.0,
+            outlet_x: 0.0,
+            delta_p: 500.0,
+            fluid_density: 1.0,
+            viscosity: 1.0,
+            report_timer: Timer::from_seconds(2.0, TimerMode::Repeating),
+        }
+    }
+}
+
+impl PressureDrive {
+    /// Keeps the inlet and outlet away from the side walls, a point held off a wall by its radius
+    /// could otherwise never pass the outlet
+    fn fit_channel(&mut self, bounds: &Bounds) {
+        let (min, max) = (
+            bounds.min_x as f32 + CHANNEL_MARGIN,
+            bounds.max_x as f32 - CHANNEL_MARGIN,
+        );
+        self.inlet_x = self.inlet_x.clamp(min, max);
+        self.outlet_x = self.outlet_x.clamp(self.inlet_x, max);
+    }
+
+    fn acceleration(&self, bounds: &Bounds) -> f32 {
+        self.delta_p / ((bounds.max_y - bounds.min_y) as f32 * self.fluid_density)
+    }
+
+    /// Pushes every point along `x` on top of gravity, zero while the drive is off
+    fn current_acceleration(&self, bounds: &Bounds) -> f32 {
+        if self.active {
+            self.acceleration(bounds)
+        } else {
+            0.0
+        }
+    }
+
+    /// `v(y) = delta_p * (H² - 4y²) / (8 * viscosity * L)` for a channel of height `H`, with `y`
+    /// measured from the channel centre
+    fn analytical_velocity(&self, y: f32, bounds: &Bounds) -> f32 {
+        let height = (bounds.max_y - bounds.min_y) as f32;
+        let length = self.outlet_x - self.inlet_x;
+        self.delta_p * (height * height - 4.0 * y * y) / (8.0 * self.viscosity * length)
+    }
+}
+
+fn pressure_drive_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    bounds: Res<Bounds>,
+    mut drive: ResMut<PressureDrive>,
+    mut points: Query<(Entity, &mut Point)>,
+) {
+    if keys.just_pressed(bindings.pressure_drive) {
+        drive.active = !drive.active;
+        drive.report_timer.reset();
+        if drive.active {
+            drive.inlet_x = bounds.min_x as f32 + CHANNEL_MARGIN;
+            drive.outlet_x = bounds.max_x as f32 - CHANNEL_MARGIN;
+        }
+    }
+    if !drive.active {
+        return;
+    }
+    // The walls can move while the drive runs
+    drive.fit_channel(&bounds);
+
+    let length = drive.outlet_x - drive.inlet_x;
+    for (entity, mut point) in points.iter_mut() {
+        if point.x > drive.outlet_x {
+            point.x -= length;
+            point.old_x -= length;
+            commands.entity(entity).insert(Immune::new());
+        }
+    }
+
+    if !drive.report_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let sub_dt = time.delta_seconds() / SUBSTEPS as f32;
+    if sub_dt <= 0.0 {
+        return;
+    }
+    let rect = bounds.to_rect();
+    let bin_height = rect.height() / PROFILE_BINS as f32;
+    let mut bins = [(0.0, 0); PROFILE_BINS];
+    let channel = drive.inlet_x..=drive.outlet_x;
+    for (_, point) in points
+        .iter()
+        .filter(|(_, point)| channel.contains(&point.x))
+    {
+        let bin = (((point.y - rect.min.y) / bin_height) as usize).min(PROFILE_BINS - 1);
+        bins[bin].0 += point.vel_x() / sub_dt;
+        bins[bin].1 += 1;
+    }
+
+    for (i, (sum, count)) in bins.iter().enumerate().filter(|(_, (_, count))| *count > 0) {
+        let y = rect.min.y + (i as f32 + 0.5) * bin_height - rect.center().y;
+        info!(
+            "Flow at y = {:.1}: {:.2} units/s measured, {:.2} analytical ({} points)",
+            y,
+            sum / *count as f32,
+            drive.analytical_velocity(y, &bounds),
+            count
+        );
+    }
+}
+
+/// Holds a point in place at a fixed temperature, acting as a heat source or sink
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct HeatReservoir {
+    temperature: f32,
+    position: Vec2,
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct HeatConduction {
+    /// Fraction of the temperature difference a touching pair evens out per second
+    conductivity: f32,
+    /// Heat that left the sources since the last report
+    source_heat: f32,
+    report_timer: Timer,
+}
+
+impl Default for HeatConduction {
+    fn default() -> Self {
+        HeatConduction {
+            conductivity: 2.0,
+            source_heat: 0.0,
+            report_timer: Timer::from_seconds(2.0, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Spawns a hot source in the bottom left corner and a cold sink in the bottom right one
+fn spawn_heat_reservoirs(commands: &mut Commands, bounds: &Bounds, spawn_timer: &mut SpawnTimer) {
+    let rect = bounds.to_rect();
+    let r = bounds.effective_corner_radius();
+    let radius = 1.0;
+    for (x, direction, temperature) in [(rect.min.x, -1.0, 1.0), (rect.max.x, 1.0, 0.0)] {
+        // As deep into the rounded corner as a point fits, so the piled up points can reach it
+        let out = Vec2::new(direction, -1.0).normalize();
+        let arc_center = Vec2::new(x - direction * r, rect.min.y + r);
+        let position = arc_center + out * (r - radius).max(0.0);
+        let mut point =
+            Point::new(spawn_timer.id, position.x, position.y, 0.0, 0.0).with_radius(radius);
+        point.temperature = temperature;
+        spawn_timer.id += 1;
+        commands.spawn((
+            create_sprite(point.radius, point.id),
+            point,
+            HeatReservoir {
+                temperature,
+                position,
+            },
+        ));
+    }
+}
+
+fn heat_reservoir_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    bounds: Res<Bounds>,
+    mut spawn_timer: ResMut<SpawnTimer>,
+    mut reservoirs: Query<(&HeatReservoir, &mut Point)>,
+) {
+    if keys.just_pressed(bindings.heat) {
+        spawn_heat_reservoirs(&mut commands, &bounds, &mut spawn_timer);
+    }
+
+    for (reservoir, mut point) in reservoirs.iter_mut() {
+        point.x = reservoir.position.x;
+        point.y = reservoir.position.y;
+        point.old_x = reservoir.position.x;
+        point.old_y = reservoir.position.y;
+        point.temperature = reservoir.temperature;
+    }
+}
+
+fn heat_conduction_system(
+    time: Res<Time>,
+    mut conduction: ResMut<HeatConduction>,
+    mut points: Query<(Entity, &mut Point, Option<&HeatReservoir>)>,
+) {
+    let (entities, shapes): (Vec<_>, Vec<_>) = points
+        .iter()
+        .map(|(entity, point, _)| (entity, (Vec2::new(point.x, point.y), point.radius)))
+        .unzip();
+    let hash = SpatialHash::new(&shapes);
+
+    let rate = conduction.conductivity * time.delta_seconds();
+    for (a, b) in hash.candidate_pairs(&shapes) {
+        let Ok([(_, mut p1, r1), (_, mut p2, r2)]) =
+            points.get_many_mut([entities[a], entities[b]])
+        else {
+            continue;
+        };
+        if !p1.colliding(&p2) {
+            continue;
+        }
+        let delta = rate * (p2.temperature - p1.temperature);
+        p1.temperature += delta;
+        p2.temperature -= delta;
+
+        // Reservoirs are reset every frame, so whatever they lose here is the heat they supplied
+        if r1.is_some_and(|r| r.temperature > AMBIENT_TEMPERATURE) {
+            conduction.source_heat -= delta;
+        }
+        if r2.is_some_and(|r| r.temperature > AMBIENT_TEMPERATURE) {
+            conduction.source_heat += delta;
+        }
+    }
+
+    if !conduction.report_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let samples: Vec<(f32, f32)> = points
+        .iter()
+        .filter(|(_, _, reservoir)| reservoir.is_none())
+        .map(|(_, point, _)| (point.x, point.temperature))
+        .collect();
+    let flux = conduction.source_heat / conduction.report_timer.duration().as_secs_f32();
+    conduction.source_heat = 0.0;
+    if samples.len() < 2 || flux == 0.0 {
+        return;
+    }
+
+    let gradient = fit_slope(&samples);
+    info!(
+        "Heat flux {:.4}/s, gradient {:.4}/unit, effective conductivity {:.4}",
+        flux,
+        gradient,
+        -flux / gradient
+    );
+}
+
+/// How close, in pixels, the cursor has to be to a wall to grab it
+const WALL_GRAB_PIXELS: f32 = 2.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Side {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+impl Side {
+    fn cursor_icon(self) -> CursorIcon {
+        match self {
+            Side::Left | Side::Right => CursorIcon::EwResize,
+            Side::Bottom | Side::Top => CursorIcon::NsResize,
+        }
+    }
+
+    /// The cursor coordinate that moves this wall
+    fn coordinate(self, cursor: Vec2) -> f32 {
+        match self {
+            Side::Left | Side::Right => cursor.x,
+            Side::Bottom | Side::Top => cursor.y,
+        }
+    }
+}
+
+impl Bounds {
+    fn wall(&self, side: Side) -> i32 {
+        match side {
+            Side::Left => self.min_x,
+            Side::Right => self.max_x,
+            Side::Bottom => self.min_y,
+            Side::Top => self.max_y,
+        }
+    }
+
+    /// Moves one wall, keeping it at least a unit away from the opposite one
+    fn set_wall(&mut self, side: Side, value: i32) {
+        match side {
+            Side::Left => self.min_x = value.min(self.max_x - 1),
+            Side::Right => self.max_x = value.max(self.min_x + 1),
+            Side::Bottom => self.min_y = value.min(self.max_y - 1),
+            Side::Top => self.max_y = value.max(self.min_y + 1),
+        }
+    }
+
+    fn wall_near(&self, cursor: Vec2, distance: f32) -> Option<Side> {
+        let rect = self.to_rect();
+        let within_x = cursor.x >= rect.min.x - distance && cursor.x <= rect.max.x + distance;
+        let within_y = cursor.y >= rect.min.y - distance && cursor.y <= rect.max.y + distance;
+        [Side::Left, Side::Right, Side::Bottom, Side::Top]
+            .into_iter()
+            .filter(|side| match side {
+                Side::Left | Side::Right => within_y,
+                Side::Bottom | Side::Top => within_x,
+            })
+            .map(|side| {
+                (
+                    side,
+                    (side.coordinate(cursor) - self.wall(side) as f32).abs(),
+                )
+            })
+            .filter(|(_, d)| *d <= distance)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(side, _)| side)
+    }
+}
+
+/// Lets the walls be dragged with the mouse
+#[derive(Resource, Default)]
+struct BoundsEditor {
+    dragging: Option<Side>,
+    /// Distance between the cursor and the wall when it was grabbed
+    drag_offset: f32,
+    hovered: Option<Side>,
+}
+
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct BoundsOutline;
+
+/// Outline of the container, in pixels
+struct RoundedRect {
+    rect: Rect,
+    radius: f32,
+}
+
+impl Geometry for RoundedRect {
+    fn add_geometry(&self, b: &mut tess::path::path::Builder) {
+        let (min, max) = (self.rect.min, self.rect.max);
+        b.add_rounded_rectangle(
+            &tess::math::Box2D::new(
+                tess::math::point(min.x, min.y),
+                tess::math::point(max.x, max.y),
+            ),
+            &tess::path::builder::BorderRadii::new(self.radius),
+            tess::path::Winding::Positive,
+        );
+    }
+}
+
+fn bounds_shape(bounds: &Bounds) -> RoundedRect {
+    let rect = bounds.to_rect();
+    RoundedRect {
+        rect: Rect {
+            min: rect.min * GAME_SCALE,
+            max: rect.max * GAME_SCALE,
+        },
+        radius: bounds.effective_corner_radius() * GAME_SCALE,
+    }
+}
+
+fn spawn_bounds_outline(mut commands: Commands, bounds: Res<Bounds>) {
+    commands.spawn((
+        GeometryBuilder::build_as(
+            &bounds_shape(&bounds),
+            DrawMode::Stroke(StrokeMode::new(Color::GRAY, 1.0)),
+            Transform::from_xyz(0.0, 0.0, -1.0),
+        ),
+        BoundsOutline,
+    ));
+}
+
+fn update_bounds_outline(bounds: Res<Bounds>, mut outline: Query<&mut Path, With<BoundsOutline>>) {
+    if !bounds.is_changed() {
+        return;
+    }
+    for mut path in outline.iter_mut() {
+        *path = ShapePath::build_as(&bounds_shape(&bounds));
+    }
+}
+
+/// Pressure on the walls, like a thermal camera pointed at a pressure vessel. The perimeter is
+/// split into `segments` pieces, counter-clockwise from the bottom left corner.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct WallHeatMap {
+    segments: usize,
+    pressures: Vec<f32>,
+    /// Fraction of the pressure left after a second
+    decay: f32,
+}
+
+impl Default for WallHeatMap {
+    fn default() -> Self {
+        WallHeatMap {
+            segments: 64,
+            pressures: vec![0.0; 64],
+            decay: 0.2,
+        }
+    }
+}
+
+impl WallHeatMap {
+    /// Distance along the perimeter of the wall that pushed with `impulse` at `position`
+    fn perimeter_distance(rect: Rect, position: Vec2, impulse: Vec2) -> f32 {
+        let (w, h) = (rect.width(), rect.height());
+        if impulse.y.abs() >= impulse.x.abs() {
+            if impulse.y > 0.0 {
+                position.x - rect.min.x
+            } else {
+                2.0 * w + h - (position.x - rect.min.x)
+            }
+        } else if impulse.x < 0.0 {
+            w + position.y - rect.min.y
+        } else {
+            2.0 * (w + h) - (position.y - rect.min.y)
+        }
+    }
+
+    /// Middle of segment `index` and the outward normal of its wall
+    fn segment_position(&self, rect: Rect, index: usize) -> (Vec2, Vec2) {
+        let (w, h) = (rect.width(), rect.height());
+        let mut s = (index as f32 + 0.5) / self.segments as f32 * 2.0 * (w + h);
+        if s < w {
+            return (Vec2::new(rect.min.x + s, rect.min.y), Vec2::NEG_Y);
+        }
+        s -= w;
+        if s < h {
+            return (Vec2::new(rect.max.x, rect.min.y + s), Vec2::X);
+        }
+        s -= h;
+        if s < w {
+            return (Vec2::new(rect.max.x - s, rect.max.y), Vec2::Y);
+        }
+        s -= w;
+        (Vec2::new(rect.min.x, rect.max.y - s), Vec2::NEG_X)
+    }
+
+    fn add_impact(&mut self, bounds: &Bounds, position: Vec2, impulse: Vec2) {
+        if self.pressures.len() != self.segments {
+            self.pressures = vec![0.0; self.segments];
+        }
+        let rect = bounds.to_rect();
+        let perimeter = 2.0 * (rect.width() + rect.height());
+        let distance = Self::perimeter_distance(rect, position, impulse).rem_euclid(perimeter);
+        let index = ((distance / perimeter * self.segments as f32) as usize).min(self.segments - 1);
+        self.pressures[index] += impulse.length();
+    }
+}
+
+/// One strip of the [`WallHeatMap`], drawn just outside the wall
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct WallHeatSegment {
+    index: usize,
+}
+
+fn spawn_wall_heat_map(mut commands: Commands, heat_map: Res<WallHeatMap>) {
+    for index in 0..heat_map.segments {
+        commands.spawn((
+            GeometryBuilder::build_as(
+                &shapes::Rectangle::default(),
+                DrawMode::Fill(FillMode::color(Color::BLUE)),
+                Transform::from_xyz(0.0, 0.0, -2.0),
+            ),
+            WallHeatSegment { index },
+        ));
+    }
+}
+
+fn wall_heat_map_system(
+    time: Res<Time>,
+    bounds: Res<Bounds>,
+    mut heat_map: ResMut<WallHeatMap>,
+    mut strips: Query<(&WallHeatSegment, &mut Path, &mut Transform, &mut DrawMode)>,
+) {
+    let keep = heat_map.decay.clamp(0.0, 1.0).powf(time.delta_seconds());
+    for pressure in heat_map.pressures.iter_mut() {
+        *pressure *= keep;
+    }
+    let hottest = heat_map
+        .pressures
+        .iter()
+        .copied()
+        .fold(f32::EPSILON, f32::max);
+
+    let rect = bounds.to_rect();
+    let length = 2.0 * (rect.width() + rect.height()) / heat_map.segments.max(1) as f32;
+    for (segment, mut path, mut transform, mut draw_mode) in strips.iter_mut() {
+        if bounds.is_changed() || heat_map.is_added() {
+            let (position, normal) = heat_map.segment_position(rect, segment.index);
+            let size = if normal.x == 0.0 {
+                Vec2::new(length, 1.0)
+            } else {
+                Vec2::new(1.0, length)
+            };
+            *path = ShapePath::build_as(&shapes::Rectangle {
+                extents: size * GAME_SCALE,
+                origin: RectangleOrigin::Center,
+            });
+            let center = (position + normal * 0.5) * GAME_SCALE;
+            transform.translation.x = center.x;
+            transform.translation.y = center.y;
+        }
+
+        let t = heat_map
+            .pressures
+            .get(segment.index)
+            .map_or(0.0, |pressure| pressure / hottest);
+        let color = Color::rgb(t, 0.0, 1.0 - t);
+        // Same as the points, only flag real changes so lyon doesn't rebuild every strip
+        if let DrawMode::Fill(fill_mode) = draw_mode.bypass_change_detection() {
+            if fill_mode.color != color {
+                fill_mode.color = color;
+                draw_mode.set_changed();
+            }
+        }
+    }
+}
+
+/// Zooms and centres the camera so the whole container is visible
+fn fit_camera(
+    bounds: &Bounds,
+    window: &Window,
+    camera: &mut Transform,
+    projection: &mut OrthographicProjection,
+) {
+    let rect = bounds.to_rect();
+    let size = rect.size() * GAME_SCALE;
+    projection.scale = (size.x / window.width()).max(size.y / window.height());
+    camera.translation.x = rect.center().x * GAME_SCALE;
+    camera.translation.y = rect.center().y * GAME_SCALE;
+}
+
+/// Walls easing from one box to another
+#[derive(Resource)]
+struct BoundsTransition {
+    from: Rect,
+    to: Rect,
+    timer: Timer,
+}
+
+/// Moves the walls along a running [`BoundsTransition`] and re-fits the camera every frame, the
+/// same adjustment a window resize or a dragged wall gets
+#[allow(clippy::too_many_arguments)]
+fn bounds_transition_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    windows: Res<Windows>,
+    mut bounds: ResMut<Bounds>,
+    transition: Option<ResMut<BoundsTransition>>,
+    points: Query<&Point>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<Camera>>,
+) {
+    if keys.just_pressed(bindings.shrink_bounds) {
+        if let Some(transition) = bounds.shrink_to_current_particles(&points) {
+            commands.insert_resource(transition);
+        }
+        return;
+    }
+    let Some(mut transition) = transition else {
+        return;
+    };
+
+    let t = transition.timer.tick(time.delta()).percent();
+    let rect = Rect {
+        min: transition.from.min.lerp(transition.to.min, t),
+        max: transition.from.max.lerp(transition.to.max, t),
+    };
+    *bounds = Bounds::from_rect(rect).with_corner_radius(bounds.corner_radius);
+    if transition.timer.finished() {
+        commands.remove_resource::<BoundsTransition>();
+    }
+
+    if let (Some(window), Ok((mut transform, mut projection))) =
+        (windows.get_primary(), camera.get_single_mut())
+    {
+        fit_camera(&bounds, window, &mut transform, &mut projection);
+    }
+}
+
+fn bounds_editor_system(
+    buttons: Res<Input<MouseButton>>,
+    mut windows: ResMut<Windows>,
+    mut editor: ResMut<BoundsEditor>,
+    mut bounds: ResMut<Bounds>,
+    mut camera: Query<(
+        &Camera,
+        &GlobalTransform,
+        &mut Transform,
+        &mut OrthographicProjection,
+    )>,
+) {
+    let Ok((camera, camera_global, mut camera_transform, mut projection)) = camera.get_single_mut()
+    else {
+        return;
+    };
+
+    if buttons.just_released(MouseButton::Left) && editor.dragging.take().is_some() {
+        if let Some(window) = windows.get_primary() {
+            fit_camera(&bounds, window, &mut camera_transform, &mut projection);
+        }
+    }
+
+    let Some(cursor) = cursor_world_position(&windows, camera, camera_global) else {
+        return;
+    };
+
+    if let Some(side) = editor.dragging {
+        let wall = (side.coordinate(cursor) - editor.drag_offset).round() as i32;
+        if wall != bounds.wall(side) {
+            bounds.set_wall(side, wall);
+        }
+        return;
+    }
+
+    let grab_distance = WALL_GRAB_PIXELS * projection.scale / GAME_SCALE;
+    let hovered = bounds.wall_near(cursor, grab_distance);
+    // Only on changes, every call queues another window command
+    if hovered != editor.hovered {
+        editor.hovered = hovered;
+        if let Some(window) = windows.get_primary_mut() {
+            window.set_cursor_icon(hovered.map_or(CursorIcon::Default, Side::cursor_icon));
+        }
+    }
+
+    if let Some(side) = hovered.filter(|_| buttons.just_pressed(MouseButton::Left)) {
+        editor.dragging = Some(side);
+        editor.drag_offset = side.coordinate(cursor) - bounds.wall(side) as f32;
+    }
+}
+
+/// Gray-Scott reaction between two species carried by the points, diffusing between touching
+/// neighbours. The points act as moving cells of the usual grid and grow Turing patterns.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct ReactionDiffusion {
+    enabled: bool,
+    diffusion_u: f32,
+    diffusion_v: f32,
+    feed: f32,
+    kill: f32,
+    /// Reaction steps per second
+    speed: f32,
+    /// Points closer than this many times their contact distance count as neighbours
+    reach: f32,
+    /// Fraction of the points seeded with `v` when enabled
+    seed_fraction: f32,
+}
+
+impl Default for ReactionDiffusion {
+    fn default() -> Self {
+        ReactionDiffusion {
+            enabled: false,
+            diffusion_u: 0.16,
+            diffusion_v: 0.08,
+            feed: 0.035,
+            kill: 0.065,
+            speed: 30.0,
+            reach: 1.25,
+            seed_fraction: 0.1,
+        }
+    }
+}
+
+fn reaction_diffusion_system(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    mut reaction: ResMut<ReactionDiffusion>,
+    mut points: Query<&mut Point>,
+) {
+    if keys.just_pressed(bindings.reaction) {
+        reaction.enabled = !reaction.enabled;
+        if reaction.enabled {
+            let mut rng = rand::thread_rng();
+            for mut point in points.iter_mut() {
+                let seeded = rng.gen::<f32>() < reaction.seed_fraction;
+                point.u = if seeded { 0.5 } else { 1.0 };
+                point.v = if seeded { 0.25 } else { 0.0 };
+            }
+        }
+    }
+    if !reaction.enabled {
+        return;
+    }
+
+    let shapes: Vec<_> = points
+        .iter()
+        .map(|point| (Vec2::new(point.x, point.y), point.radius * reaction.reach))
+        .collect();
+    let species: Vec<_> = points
+        .iter()
+        .map(|point| Vec2::new(point.u, point.v))
+        .collect();
+    let hash = SpatialHash::new(&shapes);
+
+    // Graph Laplacian over the touching neighbours
+    let mut laplacian = vec![Vec2::ZERO; shapes.len()];
+    for (a, b) in hash.candidate_pairs(&shapes) {
+        let ((pa, ra), (pb, rb)) = (shapes[a], shapes[b]);
+        if pa.distance(pb) < ra + rb {
+            let difference = species[b] - species[a];
+            laplacian[a] += difference;
+            laplacian[b] -= difference;
+        }
+    }
+
+    let steps = reaction.speed * time.delta_seconds();
+    for ((mut point, lap), (u, v)) in points
+        .iter_mut()
+        .zip(laplacian)
+        .zip(species.iter().map(|s| (s.x, s.y)))
+    {
+        let uvv = u * v * v;
+        let du = reaction.diffusion_u * lap.x - uvv + reaction.feed * (1.0 - u);
+        let dv = reaction.diffusion_v * lap.y + uvv - (reaction.feed + reaction.kill) * v;
+        point.u = (u + du * steps).clamp(0.0, 1.0);
+        point.v = (v + dv * steps).clamp(0.0, 1.0);
+    }
+}
+
+/// Where a point spawned by [`spawn_image_particles`] sits in the picture
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct ImageOrigin {
+    position: Vec2,
+}
+
+/// Spawns `target_count` resting points in the shape of the image, scaled to fit the bounds.
+/// Positions are drawn with probability proportional to the pixel's brightness.
+fn spawn_image_particles(
+    commands: &mut Commands,
+    image_path: &str,
+    bounds: &Bounds,
+    spawn_timer: &mut SpawnTimer,
+    time: f32,
+    target_count: usize,
+) -> Result<Vec<Entity>, Box<dyn Error>> {
+    let image = image::open(image_path)?.to_luma8();
+    let (width, height) = image.dimensions();
+    let weights: Vec<u32> = image.pixels().map(|pixel| pixel.0[0] as u32).collect();
+    let pixels = WeightedIndex::new(&weights)?;
+
+    let rect = bounds.to_rect();
+    let scale = (rect.width() / width as f32).min(rect.height() / height as f32);
+    // Centred, image rows run top to bottom
+    let origin = rect.center() + Vec2::new(-(width as f32), height as f32) * scale / 2.0;
+    let radius = (scale / 2.0).min(1.0);
+
+    let mut rng = rand::thread_rng();
+    let entities = (0..target_count)
+        .map(|_| {
+            let index = pixels.sample(&mut rng) as u32;
+            let pixel = Vec2::new(
+                (index % width) as f32 + rng.gen::<f32>(),
+                (index / width) as f32 + rng.gen::<f32>(),
+            );
+            let position = origin + Vec2::new(pixel.x, -pixel.y) * scale;
+
+            let point =
+                Point::new(spawn_timer.id, position.x, position.y, 0.0, 0.0).with_radius(radius);
+            spawn_timer.id += 1;
+            let entity = spawn_point(commands, point, time);
+            commands.entity(entity).insert(ImageOrigin { position });
+            entity
+        })
+        .collect();
+    Ok(entities)
+}
+
+fn image_particles_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    bounds: Res<Bounds>,
+    mut spawn_timer: ResMut<SpawnTimer>,
+    mut points: Query<(Entity, &mut Point, &ImageOrigin)>,
+) {
+    if keys.just_pressed(bindings.image) {
+        match spawn_image_particles(
+            &mut commands,
+            IMAGE_PATH,
+            &bounds,
+            &mut spawn_timer,
+            time.elapsed_seconds(),
+            IMAGE_POINTS,
+        ) {
+            Ok(entities) => info!("Spawned {} points from {}", entities.len(), IMAGE_PATH),
+            Err(e) => warn!("Could not spawn points from {}: {}", IMAGE_PATH, e),
+        }
+    }
+
+    // Freeze frame: put the picture back together
+    if keys.just_pressed(bindings.freeze_frame) {
+        for (entity, mut point, origin) in points.iter_mut() {
+            point.x = origin.position.x;
+            point.y = origin.position.y;
+            point.old_x = origin.position.x;
+            point.old_y = origin.position.y;
+            commands.entity(entity).insert(Immune::new());
+        }
+    }
+}
+
+/// Static walls read from an image, a cell is solid where its pixel is dark. Rows are stored
+/// bottom to top so `pixels[y * width + x]` is the cell `x` cells right of and `y` cells above
+/// `origin`. Empty until [`KeyBindings::maze`] loads [`MAZE_PATH`].
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+struct OccupancyGrid {
+    pixels: Vec<bool>,
+    width: usize,
+    height: usize,
+    cell_size: f32,
+    origin: Vec2,
+}
+
+impl OccupancyGrid {
+    /// Loads the image scaled to fit inside `bounds`, black pixels become walls
+    fn load(path: &str, bounds: &Bounds) -> Result<Self, Box<dyn Error>> {
+        let image = image::open(path)?.to_luma8();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let rect = bounds.to_rect();
+        let cell_size = (rect.width() / width as f32).min(rect.height() / height as f32);
+        let origin = rect.center() - Vec2::new(width as f32, height as f32) * cell_size / 2.0;
+
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, height - 1 - y)))
+            .map(|(x, row)| image.get_pixel(x as u32, row as u32).0[0] < 128)
+            .collect();
+        Ok(OccupancyGrid {
+            pixels,
+            width,
+            height,
+            cell_size,
+            origin,
+        })
+    }
+
+    fn is_occupied(&self, x: i32, y: i32) -> bool {
+        x >= 0
+            && y >= 0
+            && (x as usize) < self.width
+            && (y as usize) < self.height
+            && self.pixels[y as usize * self.width + x as usize]
+    }
+
+    fn cell_rect(&self, x: i32, y: i32) -> Rect {
+        let min = self.origin + Vec2::new(x as f32, y as f32) * self.cell_size;
+        Rect::from_corners(min, min + Vec2::splat(self.cell_size))
+    }
+
+    /// How far to move a circle out of the solid cell it overlaps the most, `None` if it is clear
+    fn push_out(&self, center: Vec2, radius: f32) -> Option<Vec2> {
+        if self.pixels.is_empty() || self.cell_size <= 0.0 {
+            return None;
+        }
+        let cell = ((center - self.origin) / self.cell_size).floor();
+        let reach = ((radius / self.cell_size).ceil() as i32).max(2);
+
+        let mut deepest: Option<(f32, Vec2)> = None;
+        for y in cell.y as i32 - reach..=cell.y as i32 + reach {
+            for x in cell.x as i32 - reach..=cell.x as i32 + reach {
+                if !self.is_occupied(x, y) {
+                    continue;
+                }
+                let rect = self.cell_rect(x, y);
+                let closest = center.clamp(rect.min, rect.max);
+                let offset = center - closest;
+                let push = if offset == Vec2::ZERO {
+                    // Centre inside the cell, leave through the nearest face
+                    let faces = [
+                        (center.x - rect.min.x, Vec2::NEG_X),
+                        (rect.max.x - center.x, Vec2::X),
+                        (center.y - rect.min.y, Vec2::NEG_Y),
+                        (rect.max.y - center.y, Vec2::Y),
+                    ];
+                    let (depth, normal) = faces
+                        .into_iter()
+                        .min_by(|a, b| a.0.total_cmp(&b.0))
+                        .unwrap();
+                    normal * (depth + radius)
+                } else if offset.length() < radius {
+                    offset.normalize() * (radius - offset.length())
+                } else {
+                    continue;
+                };
+                let depth = push.length();
+                if deepest.is_none_or(|(max, _)| depth > max) {
+                    deepest = Some((depth, push));
+                }
+            }
+        }
+        deepest.map(|(_, push)| push)
+    }
+}
+
+/// One gray tile of the [`OccupancyGrid`]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct OccupancyTile;
+
+fn spawn_occupancy_tiles(commands: &mut Commands, grid: &OccupancyGrid) {
+    for y in 0..grid.height as i32 {
+        for x in (0..grid.width as i32).filter(|&x| grid.is_occupied(x, y)) {
+            let center = grid.cell_rect(x, y).center() * GAME_SCALE;
+            commands.spawn((
+                GeometryBuilder::build_as(
+                    &shapes::Rectangle {
+                        extents: Vec2::splat(grid.cell_size * GAME_SCALE),
+                        origin: RectangleOrigin::Center,
+                    },
+                    DrawMode::Fill(FillMode::color(Color::GRAY)),
+                    Transform::from_translation(center.extend(1.0)),
+                ),
+                OccupancyTile,
+            ));
+        }
+    }
+}
+
+/// Loads the maze on [`KeyBindings::maze`], or clears it if one is loaded
+fn occupancy_grid_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    bounds: Res<Bounds>,
+    mut grid: ResMut<OccupancyGrid>,
+    tiles: Query<Entity, With<OccupancyTile>>,
+) {
+    if !keys.just_pressed(bindings.maze) {
+        return;
+    }
+    for entity in tiles.iter() {
+        commands.entity(entity).despawn();
+    }
+    if !grid.pixels.is_empty() {
+        *grid = OccupancyGrid::default();
+        return;
+    }
+    match OccupancyGrid::load(MAZE_PATH, &bounds) {
+        Ok(loaded) => {
+            spawn_occupancy_tiles(&mut commands, &loaded);
+            *grid = loaded;
+        }
+        Err(e) => warn!("Could not load {}: {}", MAZE_PATH, e),
+    }
+}
+
+/// Tiles are walls too, their impulse is tracked like in [`particle_vs_polygon_system`]
+fn occupancy_collision_system(
+    grid: Res<OccupancyGrid>,
+    damping: Res<DampingWall>,
+    mut points: Query<&mut Point>,
+    mut momentum: ResMut<MomentumTracker>,
+) {
+    for mut point in points.iter_mut() {
+        let center = Vec2::new(point.x, point.y);
+        let Some(push) = grid.push_out(center, point.radius) else {
+            continue;
+        };
+        let before = point.momentum();
+        point.x += push.x;
+        point.y += push.y;
+
+        // Bounce off the tile like off a wall
+        let normal = push.normalize_or_zero();
+        let vel = Vec2::new(point.vel_x(), point.vel_y());
+        let normal_vel = vel.dot(normal);
+        if normal_vel < 0.0 {
+            let vel = vel - normal * normal_vel * (1.0 + damping.bounce);
+            point.old_x = point.x - vel.x;
+            point.old_y = point.y - vel.y;
+        }
+        let impulse = point.momentum() - before;
+        momentum.wall_impulse_x += impulse.x;
+        momentum.wall_impulse_y += impulse.y;
+    }
+}
+
+/// A spring from a point to a fixed spot in the world, one entity per tether so a point can have
+/// several. With gravity it makes a pendulum, with a zero `rest_length` a yo-yo string.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct Tether {
+    point: Entity,
+    anchor: Vec2,
+    rest_length: f32,
+    /// Acceleration per unit of stretch, for a point of unit mass
+    stiffness: f32,
+}
+
+// Only there so reflection can build one, tethers to a missing point do nothing
+impl FromWorld for Tether {
+    fn from_world(_world: &mut World) -> Self {
+        Tether {
+            point: Entity::from_raw(u32::MAX),
+            anchor: Vec2::ZERO,
+            rest_length: 0.0,
+            stiffness: 0.0,
+        }
+    }
+}
+
+impl Tether {
+    /// Hooke's law along the tether, applied every substep by [`update_points_system`]
+    fn acceleration(&self, point: &Point) -> Vec2 {
+        let offset = self.anchor - Vec2::new(point.x, point.y);
+        let length = offset.length();
+        if length <= f32::EPSILON {
+            return Vec2::ZERO;
+        }
+        offset / length * self.stiffness * (length - self.rest_length) / point.mass
+    }
+}
+
+/// Swings the anchor of a tether from side to side
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct KinematicAnchor {
+    center: Vec2,
+    amplitude: f32,
+    /// Radians per second
+    frequency: f32,
+}
+
+fn spawn_pendulum(
+    commands: &mut Commands,
+    spawn_timer: &mut SpawnTimer,
+    time: f32,
+    anchor: Vec2,
+    length: f32,
+) {
+    let point = Point::new(spawn_timer.id, anchor.x + length, anchor.y, 0.0, 0.0);
+    spawn_timer.id += 1;
+    let point = spawn_point(commands, point, time);
+    commands.spawn((
+        GeometryBuilder::build_as(
+            &shapes::Line(Vec2::ZERO, Vec2::ZERO),
+            DrawMode::Stroke(StrokeMode::new(Color::WHITE, 1.0)),
+            Transform::from_xyz(0.0, 0.0, 1.0),
+        ),
+        Tether {
+            point,
+            anchor,
+            rest_length: length,
+            stiffness: 2000.0,
+        },
+        KinematicAnchor {
+            center: anchor,
+            amplitude: 4.0,
+            frequency: 1.0,
+        },
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn tether_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    bounds: Res<Bounds>,
+    mut spawn_timer: ResMut<SpawnTimer>,
+    mut tethers: Query<(Entity, &mut Tether, &mut Path, Option<&KinematicAnchor>)>,
+    points: Query<&Point>,
+) {
+    if keys.just_pressed(bindings.pendulum) {
+        let rect = bounds.to_rect();
+        let anchor = Vec2::new(rect.center().x, rect.max.y - 5.0);
+        spawn_pendulum(
+            &mut commands,
+            &mut spawn_timer,
+            time.elapsed_seconds(),
+            anchor,
+            15.0,
+        );
+    }
+
+    for (entity, mut tether, mut path, kinematic) in tethers.iter_mut() {
+        let Ok(point) = points.get(tether.point) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        if let Some(kinematic) = kinematic {
+            let swing = kinematic.amplitude * (kinematic.frequency * time.elapsed_seconds()).sin();
+            tether.anchor = kinematic.center + Vec2::new(swing, 0.0);
+        }
+        *path = ShapePath::build_as(&shapes::Line(
+            tether.anchor * GAME_SCALE,
+            Vec2::new(point.x, point.y) * GAME_SCALE,
+        ));
+    }
+}
+
+/// Holds a point at a constant speed in whatever direction it is moving, e.g. to drive a bead
+/// along a track. A `target_speed` of 0 stops the point without pinning it. Lives on its own
+/// entity like a [`Tether`].
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct ArcSpeedConstraint {
+    entity: Entity,
+    /// Units per second
+    target_speed: f32,
+}
+
+// Only there so reflection can build one, constraints on a missing point do nothing
+impl FromWorld for ArcSpeedConstraint {
+    fn from_world(_world: &mut World) -> Self {
+        ArcSpeedConstraint {
+            entity: Entity::from_raw(u32::MAX),
+            target_speed: 0.0,
+        }
+    }
+}
+
+/// Spawns a point that never slows down on [`KeyBindings::arc_speed`], and drops constraints whose point
+/// is gone
+#[allow(clippy::too_many_arguments)]
+fn arc_speed_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    bounds: Res<Bounds>,
+    mut spawn_timer: ResMut<SpawnTimer>,
+    constraints: Query<(Entity, &ArcSpeedConstraint)>,
+    points: Query<(), With<Point>>,
+) {
+    if keys.just_pressed(bindings.arc_speed) {
+        let center = bounds.to_rect().center();
+        let direction = Vec2::from_angle(rand::thread_rng().gen_range(0.0..std::f32::consts::TAU));
+        let point = Point::new(
+            spawn_timer.id,
+            center.x,
+            center.y,
+            direction.x * 0.1,
+            direction.y * 0.1,
+        );
+        spawn_timer.id += 1;
+        let entity = spawn_point(&mut commands, point, time.elapsed_seconds());
+        commands.spawn(ArcSpeedConstraint {
+            entity,
+            target_speed: ARC_SPEED,
+        });
+    }
+
+    for (entity, constraint) in constraints.iter() {
+        if points.get(constraint.entity).is_err() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// A point held by the mouse, `last_cursor` gives the mouse velocity
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct Dragged {
+    last_cursor: Vec2,
+}
+
+fn drag_system(
+    mut commands: Commands,
+    buttons: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    editor: Res<BoundsEditor>,
+    mut points: Query<(Entity, &mut Point, Option<&mut Dragged>)>,
+) {
+    // Before looking at the cursor, so letting go outside the window still drops the point
+    if buttons.just_released(MouseButton::Left) {
+        for (entity, _, dragged) in points.iter() {
+            if dragged.is_some() {
+                commands.entity(entity).remove::<Dragged>();
+            }
+        }
+    }
+
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+    let Some(cursor) = cursor_world_position(&windows, camera, camera_transform) else {
+        return;
+    };
+
+    // A grabbed wall takes the click
+    if buttons.just_pressed(MouseButton::Left) && editor.dragging.is_none() {
+        let picked = points
+            .iter()
+            .map(|(entity, point, _)| (entity, Vec2::new(point.x, point.y).distance(cursor), point))
+            .filter(|(_, distance, point)| *distance < point.radius)
+            .min_by(|(_, a, _), (_, b, _)| a.total_cmp(b));
+        if let Some((entity, _, _)) = picked {
+            commands.entity(entity).insert(Dragged {
+                last_cursor: cursor,
+            });
+        }
+    }
+
+    for (_, mut point, dragged) in points.iter_mut() {
+        let Some(mut dragged) = dragged else {
+            continue;
+        };
+
+        // Velocity is per substep, so spread the cursor movement over them. On release the point
+        // simply keeps this velocity.
+        let vel = (cursor - dragged.last_cursor) / SUBSTEPS as f32;
+        point.x = cursor.x;
+        point.y = cursor.y;
+        point.old_x = cursor.x - vel.x;
+        point.old_y = cursor.y - vel.y;
+        dragged.last_cursor = cursor;
+    }
+}
+
+/// Reads the fill height of a column like a measuring cylinder: the highest point in it
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct FillLevelSensor {
+    column_x: f32,
+    column_width: f32,
+    level: f32,
+}
+
+impl Default for FillLevelSensor {
+    fn default() -> Self {
+        FillLevelSensor {
+            column_x: 0.0,
+            column_width: 4.0,
+            level: 0.0,
+        }
+    }
+}
+
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct FillLevelLine;
+
+fn spawn_fill_level_line(mut commands: Commands, sensor: Res<FillLevelSensor>) {
+    let half_width = sensor.column_width / 2.0 * GAME_SCALE;
+    commands.spawn((
+        GeometryBuilder::build_as(
+            &shapes::Line(Vec2::new(-half_width, 0.0), Vec2::new(half_width, 0.0)),
+            DrawMode::Stroke(StrokeMode::new(Color::RED, 2.0)),
+            Transform::default(),
+        ),
+        FillLevelLine,
+    ));
+}
+
+fn fill_level_system(
+    mut sensor: ResMut<FillLevelSensor>,
+    bounds: Res<Bounds>,
+    points: Query<&Point>,
+    mut line: Query<&mut Transform, With<FillLevelLine>>,
+) {
+    let half_width = sensor.column_width / 2.0;
+    let column = (sensor.column_x - half_width)..=(sensor.column_x + half_width);
+    sensor.level = points
+        .iter()
+        .filter(|point| column.contains(&point.x))
+        .map(|point| point.y)
+        .fold(bounds.min_y as f32, f32::max);
+
+    for mut transform in line.iter_mut() {
+        transform.translation =
+            Vec3::new(sensor.column_x * GAME_SCALE, sensor.level * GAME_SCALE, 1.0);
+    }
+}
+
+/// Measures how long a point takes to reach the right wall, removed once it has
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct FlightTimer {
+    start_x: f32,
+    start_time: f32,
+}
+
+impl FlightTimer {
+    fn start(point: &Point, start_time: f32) -> Self {
+        FlightTimer {
+            start_x: point.x,
+            start_time,
+        }
+    }
+}
+
+struct FlightMeasured {
+    entity: Entity,
+    tof: f32,
+    mean_vel: f32,
+}
+
+/// Points that touched the right wall in any substep of this frame. They may well have bounced
+/// off again by the end of the frame, so this is the only place a crossing shows up.
+#[derive(Resource, Default)]
+struct RightWallContacts {
+    entities: HashSet<Entity>,
+}
+
+/// Rolling mean over the last [`FLIGHT_MEAN_WINDOW`] times of flight
+#[derive(Resource, Default)]
+struct FlightStats {
+    recent: VecDeque<f32>,
+}
+
+impl FlightStats {
+    fn mean_tof(&self) -> f32 {
+        self.recent.iter().sum::<f32>() / self.recent.len().max(1) as f32
+    }
+}
+
+fn flight_timer_system(
+    mut commands: Commands,
+    query: Query<(Entity, &Point, &FlightTimer)>,
+    right_wall: Res<RightWallContacts>,
+    time: Res<Time>,
+    mut measured: EventWriter<FlightMeasured>,
+) {
+    for (entity, point, flight) in query.iter() {
+        // Touching the wall counts as crossing, the walls keep the centre from ever passing it
+        if !right_wall.entities.contains(&entity) {
+            continue;
+        }
+
+        let tof = time.elapsed_seconds() - flight.start_time;
+        measured.send(FlightMeasured {
+            entity,
+            tof,
+            mean_vel: (point.x - flight.start_x) / tof.max(f32::EPSILON),
+        });
+        commands.entity(entity).remove::<FlightTimer>();
+    }
+}
+
+/// HUD line in the top left corner with the rolling mean of [`FlightStats`]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct FlightStatsText;
+
+fn spawn_flight_stats_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "Mean time of flight: -",
+            TextStyle {
+                font: asset_server.load(HUD_FONT_PATH),
+                font_size: 18.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(5.0),
+                left: Val::Px(5.0),
+                ..default()
+            },
+            ..default()
+        }),
+        FlightStatsText,
+    ));
+}
+
+fn flight_stats_system(
+    mut measured: EventReader<FlightMeasured>,
+    mut stats: ResMut<FlightStats>,
+    mut text: Query<&mut Text, With<FlightStatsText>>,
+) {
+    for flight in measured.iter() {
+        stats.recent.push_back(flight.tof);
+        if stats.recent.len() > FLIGHT_MEAN_WINDOW {
+            stats.recent.pop_front();
+        }
+        info!(
+            "{:?} crossed in {:.2}s at {:.2} units/s, mean time of flight {:.2}s",
+            flight.entity,
+            flight.tof,
+            flight.mean_vel,
+            stats.mean_tof()
+        );
+        for mut text in text.iter_mut() {
+            text.sections[0].value = format!(
+                "Mean time of flight: {:.2}s over the last {} points",
+                stats.mean_tof(),
+                stats.recent.len()
+            );
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Reflect)]
+enum SimulationPhase {
+    /// Spawn particles
+    Fill,
+    /// Lower the top of the bounds like a piston
+    Compress,
+    /// Put the bounds back where they were
+    Release,
+    /// No spawning, statistics are recorded at the end
+    Measure,
+}
+
+impl SimulationPhase {
+    fn next(self) -> Self {
+        match self {
+            SimulationPhase::Fill => SimulationPhase::Compress,
+            SimulationPhase::Compress => SimulationPhase::Release,
+            SimulationPhase::Release => SimulationPhase::Measure,
+            SimulationPhase::Measure => SimulationPhase::Fill,
+        }
+    }
+}
+
+/// Runs the fill -> compress -> release -> measure experiment on repeat while `active`
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+struct PhaseController {
+    active: bool,
+    phase: SimulationPhase,
+    timer: Timer,
+    /// Bounds before compressing, restored on release
+    rest_bounds: Rect,
+    cycles: u32,
+}
+
+impl Default for PhaseController {
+    fn default() -> Self {
+        PhaseController {
+            active: false,
+            phase: SimulationPhase::Fill,
+            timer: Timer::from_seconds(PHASE_SECONDS, TimerMode::Once),
+            rest_bounds: Rect::default(),
+            cycles: 0,
+        }
+    }
+}
+
+impl PhaseController {
+    fn spawning(&self) -> bool {
+        !self.active || self.phase == SimulationPhase::Fill
+    }
+}
+
+fn phase_controller_system(
+    mut controller: ResMut<PhaseController>,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    mut bounds: ResMut<Bounds>,
+    points: Query<&Point>,
+) {
+    if keys.just_pressed(bindings.phase) {
+        if controller.active && controller.phase == SimulationPhase::Compress {
+            *bounds =
+                Bounds::from_rect(controller.rest_bounds).with_corner_radius(bounds.corner_radius);
+        }
+        controller.active = !controller.active;
+        controller.phase = SimulationPhase::Fill;
+        controller.timer.reset();
+        info!("Phase controller active: {}", controller.active);
+    }
+    if !controller.active {
+        return;
+    }
+
+    controller.timer.tick(time.delta());
+    if controller.phase == SimulationPhase::Compress {
+        let rest = controller.rest_bounds;
+        let compressed_top = rest.min.y + rest.height() * COMPRESSED_HEIGHT;
+        let top = rest.max.y + (compressed_top - rest.max.y) * controller.timer.percent();
+        bounds.max_y = top.round() as i32;
+    }
+    if !controller.timer.finished() {
+        return;
+    }
+
+    match controller.phase {
+        SimulationPhase::Fill => controller.rest_bounds = bounds.to_rect(),
+        SimulationPhase::Compress => {
+            *bounds =
+                Bounds::from_rect(controller.rest_bounds).with_corner_radius(bounds.corner_radius)
+        }
+        SimulationPhase::Release => {}
+        SimulationPhase::Measure => {
+            controller.cycles += 1;
+            let count = points.iter().count();
+            let mean_speed = points
+                .iter()
+                .map(|point| (point.vel_x() * point.vel_x() + point.vel_y() * point.vel_y()).sqrt())
+                .sum::<f32>()
+                / count.max(1) as f32;
+            let max_height = points.iter().map(|point| point.y).fold(f32::MIN, f32::max);
+            info!(
+                "Cycle {}: {} points, mean speed {:.4}, highest point {:.2}",
+                controller.cycles, count, mean_speed, max_height
+            );
+        }
+    }
+
+    controller.phase = controller.phase.next();
+    controller.timer.reset();
+    info!("Simulation phase: {:?}", controller.phase);
+}
+
+/// Keeps the whole container in view when the primary window is created or resized, the bounds
+/// themselves stay as configured
+fn fit_camera_on_resize(
+    windows: Res<Windows>,
+    bounds: Res<Bounds>,
+    mut window_resize: EventReader<WindowResized>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<Camera>>,
+) {
+    if !window_resize.iter().any(|e| e.id == WindowId::primary()) {
+        return;
+    }
+    if let (Some(window), Ok((mut transform, mut projection))) =
+        (windows.get_primary(), camera.get_single_mut())
+    {
+        fit_camera(&bounds, window, &mut transform, &mut projection);
+    }
+}
+
+/// Keys for the built-in experiments and toggles, see the Controls table in the README. The
+/// reflect editor's arrow and `+`/`-` keys and the [`BurstSpawn`] key are set elsewhere.
+#[derive(Resource, Clone, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct KeyBindings {
+    pub arc_speed: KeyCode,
+    pub brazil_nut: KeyCode,
+    pub color_mode: KeyCode,
+    pub freeze_frame: KeyCode,
+    pub heat: KeyCode,
+    pub image: KeyCode,
+    pub lennard_jones: KeyCode,
+    pub maze: KeyCode,
+    pub momentum: KeyCode,
+    pub obstacle: KeyCode,
+    pub pendulum: KeyCode,
+    pub phase: KeyCode,
+    pub pressure_drive: KeyCode,
+    pub reaction: KeyCode,
+    pub shrink_bounds: KeyCode,
+    pub tilt: KeyCode,
+    pub wave: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            arc_speed: KeyCode::A,
+            brazil_nut: KeyCode::N,
+            color_mode: KeyCode::C,
+            freeze_frame: KeyCode::P,
+            heat: KeyCode::H,
+            image: KeyCode::G,
+            lennard_jones: KeyCode::L,
+            maze: KeyCode::M,
+            momentum: KeyCode::V,
+            obstacle: KeyCode::O,
+            pendulum: KeyCode::K,
+            phase: KeyCode::E,
+            pressure_drive: KeyCode::F,
+            reaction: KeyCode::R,
+            shrink_bounds: KeyCode::S,
+            tilt: KeyCode::T,
+            wave: KeyCode::W,
+        }
+    }
+}
+
+/// Settings [`VerletPlugin`] starts the simulation with. Each of them seeds a resource that can
+/// still be changed at runtime, e.g. through the reflect editor.
+#[derive(Clone, Debug)]
+pub struct VerletConfig {
+    /// Container the points live in, in simulation units. Rounded inwards to whole units.
+    pub bounds: Rect,
+    pub corner_radius: f32,
+    pub gravity: Vec2,
+    /// Velocity kept per substep on each axis
+    pub friction: Vec2,
+    /// Fraction of the speed into a wall that it sends back
+    pub bounce: f32,
+    pub spawn_interval: Duration,
+    /// Id of the first spawned point, ids also pick the default colors
+    pub first_spawn_id: i32,
+    /// Spawner settings file that is watched for changes, `None` to keep the built-in settings
+    pub spawn_config_path: Option<PathBuf>,
+    pub max_collisions_per_substep: u32,
+    /// Draw a drop shadow behind every point, which doubles the number of shape entities
+    pub shadows: bool,
+    pub keys: KeyBindings,
+}
+
+impl Default for VerletConfig {
+    fn default() -> Self {
+        VerletConfig {
+            bounds: Rect::new(-40.0, -12.0, 40.0, 40.0),
+            corner_radius: 5.0,
+            gravity: Vec2::new(0.0, GRAVITY),
+            friction: Vec2::splat(FRICTION),
+            bounce: BOUNCE,
+            spawn_interval: Duration::from_millis(500),
+            first_spawn_id: 10,
+            spawn_config_path: Some(SPAWN_CONFIG_PATH.into()),
+            max_collisions_per_substep: MAX_COLLISIONS_PER_SUBSTEP,
+            shadows: false,
+            keys: KeyBindings::default(),
+        }
+    }
+}
+
+/// Physics, spawning, rendering of the points and all the built-in experiments
+#[derive(Default)]
+pub struct VerletPlugin {
+    pub config: VerletConfig,
+}
+
+impl Plugin for VerletPlugin {
+    fn build(&self, app: &mut App) {
+        let config = &self.config;
+        if config.shadows {
+            app.init_resource::<ShadowConfig>();
+        }
+        app.insert_resource(config.keys.clone())
+            .insert_resource(
+                Bounds::from_rect(config.bounds).with_corner_radius(config.corner_radius),
+            )
+            .insert_resource(SpawnTimer {
+                timer: Timer::new(config.spawn_interval, TimerMode::Repeating),
+                id: config.first_spawn_id,
+                jitter_fraction: 0.0,
+                burst_size: 1,
+            })
+            .insert_resource(PointSpawner {
+                config_path: config.spawn_config_path.clone(),
+                ..default()
+            })
+            .insert_resource(CollisionBudget {
+                max_per_substep: config.max_collisions_per_substep,
+                exceeded_frames: 0,
+            })
+            .insert_resource(GravityConfig {
+                x: config.gravity.x,
+                y: config.gravity.y,
+            })
+            .insert_resource(FrictionConfig {
+                friction_x: config.friction.x,
+                friction_y: config.friction.y,
+            })
+            .init_resource::<MomentumTracker>()
+            .init_resource::<CategoryGravity>()
+            .init_resource::<LennardJones>()
+            .init_resource::<AdaptiveTimestep>()
+            .init_resource::<WallHeatMap>()
+            .init_resource::<ReactionDiffusion>()
+            .insert_resource(DampingWall {
+                bounce: config.bounce,
+                ..default()
+            })
+            .init_resource::<OccupancyGrid>()
+            .init_resource::<SystemMomentum>()
+            .insert_resource(
+                ReflectEditor::default()
+                    .with::<GravityConfig>()
+                    .with::<FrictionConfig>()
+                    .with::<DampingWall>()
+                    .with::<Bounds>()
+                    .with::<PointSpawner>()
+                    .with::<SpawnTimer>()
+                    .with::<CollisionBudget>()
+                    .with::<MomentumTracker>()
+                    .with::<SystemMomentum>()
+                    .with::<BurstSpawn>()
+                    .with::<FillLevelSensor>()
+                    .with::<PhaseController>()
+                    .with::<RenderDistance>()
+                    .with::<BrazilNutDemo>()
+                    .with::<TiltExperiment>()
+                    .with::<WaveDemo>()
+                    .with::<PressureDrive>()
+                    .with::<HeatConduction>()
+                    .with::<LennardJones>()
+                    .with::<AdaptiveTimestep>()
+                    .with::<WallHeatMap>()
+                    .with::<ReactionDiffusion>(),
+            )
+            .init_resource::<BurstSpawn>()
+            .init_resource::<FlightStats>()
+            .init_resource::<RightWallContacts>()
+            .init_resource::<FillLevelSensor>()
+            .init_resource::<PhaseController>()
+            .init_resource::<ColorMode>()
+            .init_resource::<RenderDistance>()
+            .init_resource::<BrazilNutDemo>()
+            .init_resource::<TiltExperiment>()
+            .init_resource::<WaveDemo>()
+            .init_resource::<PressureDrive>()
+            .init_resource::<HeatConduction>()
+            .init_resource::<BoundsEditor>()
+            .add_event::<FlightMeasured>()
+            .register_type::<Point>()
+            .register_type::<KeyBindings>()
+            .register_type::<HighDetailRender>()
+            .register_type::<ShadowEntity>()
+            .register_type::<PolygonObstacle>()
+            .register_type::<HeatReservoir>()
+            .register_type::<BoundsOutline>()
+            .register_type::<Dragged>()
+            .register_type::<FillLevelLine>()
+            .register_type::<FlightTimer>()
+            .register_type::<ImageOrigin>()
+            .register_type::<Bounds>()
+            .register_type::<GravityConfig>()
+            .register_type::<CategoryGravity>()
+            .register_type::<LennardJones>()
+            .register_type::<AdaptiveTimestep>()
+            .register_type::<WallHeatMap>()
+            .register_type::<WallHeatSegment>()
+            .register_type::<Tether>()
+            .register_type::<ArcSpeedConstraint>()
+            .register_type::<Immune>()
+            .register_type::<OccupancyGrid>()
+            .register_type::<OccupancyTile>()
+            .register_type::<KinematicAnchor>()
+            .register_type::<ReactionDiffusion>()
+            .register_type::<FrictionConfig>()
+            .register_type::<DampingWall>()
+            .register_type::<SpawnTimer>()
+            .register_type::<SpawnPattern>()
+            .register_type::<SpawnDistribution>()
+            .register_type::<PointSpawner>()
+            .register_type::<CollisionBudget>()
+            .register_type::<MomentumTracker>()
+            .register_type::<SystemMomentum>()
+            .register_type::<MomentumArrow>()
+            .register_type::<MomentumPlot>()
+            .register_type::<SegregationPlot>()
+            .register_type::<FlightStatsText>()
+            .register_type::<BurstSpawn>()
+            .register_type::<FillLevelSensor>()
+            .register_type::<SimulationPhase>()
+            .register_type::<PhaseController>()
+            .register_type::<RenderDistance>()
+            .register_type::<BrazilNutDemo>()
+            .register_type::<TiltExperiment>()
+            .register_type::<WaveDemo>()
+            .register_type::<PressureDrive>()
+            .register_type::<HeatConduction>()
+            .add_plugin(ShapePlugin)
+            .add_startup_system(add_points)
+            .add_startup_system(load_spawn_config)
+            .add_startup_system(watch_spawn_config)
+            .add_startup_system(spawn_fill_level_line)
+            .add_startup_system(spawn_bounds_outline)
+            .add_startup_system(spawn_wall_heat_map)
+            .add_startup_system(spawn_momentum_display)
+            .add_startup_system(spawn_segregation_plot)
+            .add_startup_system(spawn_flight_stats_text)
+            .add_system(fit_camera_on_resize)
+            .add_system(phase_controller_system.before(update_points_system))
+            .add_system(lennard_jones_toggle_system.before(update_points_system))
+            .add_system(tether_system.before(update_points_system))
+            .add_system(arc_speed_system.before(update_points_system))
+            .add_system(immune_system.before(update_points_system))
+            .add_system(update_points_system)
+            .add_system(momentum_check_system.after(update_points_system))
+            .add_system(system_momentum_system.after(update_points_system))
+            .add_system(wall_heat_map_system.after(update_points_system))
+            .add_system(update_visual_point)
+            .add_system(reaction_diffusion_system.after(update_points_system))
+            .add_system(
+                update_color_system
+                    .after(update_points_system)
+                    .after(reaction_diffusion_system),
+            )
+            .add_system(lod_render_system.after(update_color_system))
+            .add_system(spawn_shadows)
+            .add_system(update_shadows)
+            .add_system(reflect_editor_system)
+            .add_system(burst_spawn_system)
+            .add_system(brazil_nut_system.before(update_points_system))
+            .add_system(tilt_system.before(update_points_system))
+            .add_system(pressure_drive_system.before(update_points_system))
+            .add_system(wave_system.after(update_points_system))
+            // Both teleport points, which only the next frame's momentum check may see
+            .add_system(image_particles_system.after(momentum_check_system))
+            .add_system(heat_reservoir_system.after(momentum_check_system))
+            .add_system(heat_conduction_system.after(heat_reservoir_system))
+            .add_system(polygon_obstacle_system.before(update_points_system))
+            .add_system(
+                particle_vs_polygon_system
+                    .after(update_points_system)
+                    .before(momentum_check_system),
+            )
+            .add_system(occupancy_grid_system.before(update_points_system))
+            .add_system(
+                occupancy_collision_system
+                    .after(update_points_system)
+                    .before(momentum_check_system),
+            )
+            .add_system(avalanche_detector_system.after(update_points_system))
+            .add_system(bounds_editor_system.before(drag_system))
+            .add_system(bounds_transition_system.before(update_points_system))
+            .add_system(
+                update_bounds_outline
+                    .after(bounds_editor_system)
+                    .after(bounds_transition_system),
+            )
+            .add_system(drag_system.before(update_points_system))
+            .add_system(fill_level_system.after(update_points_system))
+            .add_system(flight_timer_system.after(update_points_system))
+            .add_system(flight_stats_system.after(flight_timer_system))
+            .add_system(reload_spawn_config_system)
+            .add_system(spawn_item.after(reload_spawn_config_system));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_rect_round_trip() {
+        let bounds = Bounds::new(-40, -12, 40, 40);
+        assert_eq!(Bounds::from_rect(bounds.to_rect()), bounds);
+    }
+
+    #[test]
+    fn bounds_from_rect_rounds_inwards() {
+        let bounds = Bounds::from_rect(Rect::new(-3.5, -2.2, 4.7, 6.9));
+        assert_eq!(bounds, Bounds::new(-3, -2, 4, 6));
+    }
+
+    fn overlap_at(x: f32, radius: f32) -> f32 {
+        let bounds = Bounds::new(0, 0, 10, 10);
+        bounds.overlap_fraction(&Point::new(0, x, 5.0, 0.0, 0.0).with_radius(radius))
+    }
+
+    #[test]
+    fn overlap_fraction_fully_inside() {
+        assert_eq!(overlap_at(5.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn overlap_fraction_partially_overlapping() {
+        // Circular segment past a chord half a radius from the centre
+        let expected = (std::f32::consts::FRAC_PI_3 - 0.75_f32.sqrt() / 2.0) / std::f32::consts::PI;
+        assert!((overlap_at(0.5, 1.0) - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn overlap_fraction_centre_on_wall() {
+        assert!((overlap_at(0.0, 1.0) - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn overlap_fraction_fully_outside() {
+        assert!((overlap_at(-2.0, 1.0) - 1.0).abs() < 1e-5);
+    }
+
+    /// Random points in a 40 by 40 box, each radius picked from `radii`
+    fn random_points(radii: &[f32], count: usize) -> Vec<(Vec2, f32)> {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(217);
+        (0..count)
+            .map(|_| {
+                let position = Vec2::new(rng.gen_range(0.0..40.0), rng.gen_range(0.0..40.0));
+                (position, *radii.choose(&mut rng).unwrap())
+            })
+            .collect()
+    }
+
+    fn assert_finds_every_touching_pair(points: &[(Vec2, f32)]) {
+        let pairs = SpatialHash::new(points).candidate_pairs(points);
+        let found: HashSet<_> = pairs.iter().map(|&(a, b)| (a.min(b), a.max(b))).collect();
+        assert_eq!(found.len(), pairs.len(), "a pair was reported twice");
+
+        let mut touching = 0;
+        for a in 0..points.len() {
+            for b in a + 1..points.len() {
+                let ((pa, ra), (pb, rb)) = (points[a], points[b]);
+                if pa.distance(pb) < ra + rb {
+                    touching += 1;
+                    assert!(found.contains(&(a, b)), "missed touching pair {a}, {b}");
+                }
+            }
+        }
+        assert!(touching > 0);
+    }
+
+    #[test]
+    fn spatial_hash_matches_brute_force_for_mixed_radii() {
+        assert_finds_every_touching_pair(&random_points(&[0.2, 1.0], 1000));
+    }
+
+    #[test]
+    fn spatial_hash_matches_brute_force_for_equal_radii() {
+        assert_finds_every_touching_pair(&random_points(&[0.5], 1000));
+    }
+
+    /// Normal velocity of a point that moved diagonally out of the top right corner
+    fn corner_bounce(damping: DampingWall) -> f32 {
+        let bounds = Bounds::new(0, 0, 10, 10).with_corner_radius(2.0);
+        let friction = FrictionConfig {
+            friction_x: 1.0,
+            friction_y: 1.0,
+        };
+        let mut point = Point::new(0, 9.5, 9.5, 0.1, 0.1);
+        bounds.constrain_corner(&mut point, &friction, &damping);
+        Vec2::new(point.vel_x(), point.vel_y()).dot(Vec2::ONE.normalize())
+    }
+
+    #[test]
+    fn corner_bounces_like_the_walls() {
+        let speed = Vec2::new(0.1, 0.1).length();
+        assert!((corner_bounce(DampingWall::default()) + speed * BOUNCE).abs() < 1e-5);
+    }
+
+    #[test]
+    fn corner_uses_the_damping_of_both_sides() {
+        let one_side = DampingWall {
+            sides: [0.0, 1.0, 0.0, 0.0],
+            ..default()
+        };
+        let speed = Vec2::new(0.1, 0.1).length();
+        assert!((corner_bounce(one_side) + speed * BOUNCE / 2.0).abs() < 1e-5);
+        let both_sides = DampingWall {
+            sides: [0.0, 1.0, 0.0, 1.0],
+            ..default()
+        };
+        assert!(corner_bounce(both_sides).abs() < 1e-5);
+    }
+
+    #[test]
+    fn poiseuille_profile_vanishes_at_the_walls() {
+        let bounds = Bounds::new(0, 0, 20, 10);
+        let drive = PressureDrive {
+            inlet_x: 2.0,
+            outlet_x: 18.0,
+            ..default()
+        };
+        assert!(drive.analytical_velocity(5.0, &bounds).abs() < 1e-3);
+        assert!(drive.analytical_velocity(-5.0, &bounds).abs() < 1e-3);
+        let centre = drive.delta_p * 100.0 / (8.0 * drive.viscosity * 16.0);
+        assert!((drive.analytical_velocity(0.0, &bounds) - centre).abs() < 1e-3);
+    }
+
+    /// CIE L*a*b* of an sRGB color, D65 white point
+    fn to_lab(color: Color) -> Vec3 {
+        let linear = |c: f32| {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let rgb = Vec3::new(linear(color.r()), linear(color.g()), linear(color.b()));
+        let xyz = Mat3::from_cols(
+            Vec3::new(0.4124, 0.2126, 0.0193),
+            Vec3::new(0.3576, 0.7152, 0.1192),
+            Vec3::new(0.1805, 0.0722, 0.9505),
+        ) * rgb
+            / Vec3::new(0.95047, 1.0, 1.08883);
+        let f = |t: f32| {
+            if t > 216.0 / 24389.0 {
+                t.cbrt()
+            } else {
+                (24389.0 / 27.0 * t + 16.0) / 116.0
+            }
+        };
+        let (fx, fy, fz) = (f(xyz.x), f(xyz.y), f(xyz.z));
+        Vec3::new(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+    }
+
+    #[test]
+    fn id_to_color_neighbours_are_distinct() {
+        let min_delta_e = (0..256)
+            .map(|id| to_lab(id_to_color(id)).distance(to_lab(id_to_color(id + 1))))
+            .fold(f32::INFINITY, f32::min);
+        assert!(min_delta_e > 10.0, "delta E {}", min_delta_e);
+    }
+
+    #[test]
+    fn overlap_fraction_zero_radius() {
+        assert_eq!(overlap_at(5.0, 0.0), 0.0);
+        assert_eq!(overlap_at(-1.0, 0.0), 1.0);
+    }
+}